@@ -0,0 +1,312 @@
+use rust_snake::game::{FoodKind, GameEvent};
+
+/// Effects are synthesized from short tone sequences, so no sound files have
+/// to ship with the game. Each note is `(frequency in Hz, length in ms)`; a
+/// frequency of 0 is a rest.
+pub type Note = (f32, u64);
+
+static EAT_NOTES: &[Note] = &[(660.0, 50), (990.0, 70)];
+static BONUS_NOTES: &[Note] = &[(784.0, 50), (988.0, 50), (1319.0, 90)];
+static SHRINK_NOTES: &[Note] = &[(523.0, 60), (392.0, 60), (262.0, 80)];
+static SPEED_UP_NOTES: &[Note] = &[(440.0, 40), (554.0, 40), (659.0, 40), (880.0, 60)];
+static SLOW_DOWN_NOTES: &[Note] = &[(440.0, 120), (330.0, 180)];
+static POWER_UP_NOTES: &[Note] = &[
+    (392.0, 60),
+    (523.0, 60),
+    (784.0, 60),
+    (523.0, 60),
+    (784.0, 100),
+];
+static TURN_NOTES: &[Note] = &[(220.0, 20)];
+static DEATH_NOTES: &[Note] = &[(392.0, 120), (311.0, 120), (233.0, 260)];
+static VICTORY_NOTES: &[Note] = &[(523.0, 100), (659.0, 100), (784.0, 100), (1047.0, 300)];
+static MUSIC_NOTES: &[Note] = &[
+    (262.0, 200),
+    (0.0, 50),
+    (330.0, 200),
+    (0.0, 50),
+    (392.0, 200),
+    (0.0, 50),
+    (330.0, 200),
+    (0.0, 300),
+    (294.0, 200),
+    (0.0, 50),
+    (349.0, 200),
+    (0.0, 50),
+    (440.0, 200),
+    (0.0, 50),
+    (349.0, 200),
+    (0.0, 300),
+];
+/// Music plays quieter than effects so it doesn't drown them out.
+static MUSIC_VOLUME: f32 = 0.3;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Sound {
+    Eat,
+    Bonus,
+    Shrink,
+    SpeedUp,
+    SlowDown,
+    PowerUp,
+    Turn,
+    Death,
+    Victory,
+}
+
+pub fn sound_for(event: &GameEvent) -> Sound {
+    match event {
+        GameEvent::AteFood { kind, .. } => match kind {
+            FoodKind::Normal => Sound::Eat,
+            FoodKind::Golden => Sound::Bonus,
+            FoodKind::Shrink => Sound::Shrink,
+            FoodKind::SpeedBoost => Sound::SpeedUp,
+            FoodKind::SlowMotion => Sound::SlowDown,
+            FoodKind::Ghost | FoodKind::Shield | FoodKind::Magnet => Sound::PowerUp,
+        },
+        GameEvent::Turned { .. } => Sound::Turn,
+        GameEvent::Died { .. } => Sound::Death,
+        GameEvent::Won { .. } => Sound::Victory,
+    }
+}
+
+pub fn notes(sound: Sound) -> &'static [Note] {
+    match sound {
+        Sound::Eat => EAT_NOTES,
+        Sound::Bonus => BONUS_NOTES,
+        Sound::Shrink => SHRINK_NOTES,
+        Sound::SpeedUp => SPEED_UP_NOTES,
+        Sound::SlowDown => SLOW_DOWN_NOTES,
+        Sound::PowerUp => POWER_UP_NOTES,
+        Sound::Turn => TURN_NOTES,
+        Sound::Death => DEATH_NOTES,
+        Sound::Victory => VICTORY_NOTES,
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct AudioSettings {
+    /// Between 0.0 and 1.0.
+    pub volume: f32,
+    pub muted: bool,
+    pub music: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            volume: 0.7,
+            muted: false,
+            music: true,
+        }
+    }
+}
+
+impl AudioSettings {
+    pub fn effects_volume(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.volume
+        }
+    }
+
+    pub fn music_volume(&self) -> f32 {
+        if self.music {
+            self.effects_volume() * MUSIC_VOLUME
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Plays effects and music when an output device is available and does
+/// nothing otherwise, so the game runs the same headless, in CI or when built
+/// without the `audio` feature.
+pub struct Audio {
+    settings: AudioSettings,
+    output: Option<backend::Output>,
+}
+
+impl Audio {
+    pub fn new(settings: AudioSettings) -> Audio {
+        let output = match backend::Output::open() {
+            Ok(mut output) => {
+                output.start_music(MUSIC_NOTES, settings.music_volume());
+                Some(output)
+            }
+            Err(err) => {
+                if backend::ENABLED {
+                    eprintln!("No audio output, playing without sound.");
+                    eprintln!("{err}");
+                }
+                None
+            }
+        };
+        Audio { settings, output }
+    }
+
+    pub fn play(&self, sound: Sound) {
+        let volume = self.settings.effects_volume();
+        if let (Some(output), true) = (&self.output, volume > 0.0) {
+            output.play(notes(sound), volume);
+        }
+    }
+
+    /// Plays the effects for what happened during the last tick.
+    pub fn play_events(&self, events: &[GameEvent]) {
+        for event in events {
+            self.play(sound_for(event));
+        }
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.settings.muted = !self.settings.muted;
+        if let Some(output) = &self.output {
+            output.set_music_volume(self.settings.music_volume());
+        }
+    }
+}
+
+#[cfg(feature = "audio")]
+mod backend {
+    use rodio::source::{from_iter, SineWave, Source};
+    use rodio::{OutputStream, OutputStreamHandle, Sink};
+    use std::time::Duration;
+
+    use super::Note;
+
+    pub static ENABLED: bool = true;
+
+    pub struct Output {
+        // Dropping the stream stops all playback, so it is kept alive here.
+        _stream: OutputStream,
+        handle: OutputStreamHandle,
+        music: Option<Sink>,
+    }
+
+    fn tones(notes: &'static [Note], volume: f32) -> impl Source<Item = f32> + Send {
+        from_iter(notes.iter().map(move |(frequency, millis)| {
+            let amplitude = if *frequency > 0.0 { volume * 0.2 } else { 0.0 };
+            SineWave::new(frequency.max(1.0))
+                .take_duration(Duration::from_millis(*millis))
+                .amplify(amplitude)
+        }))
+    }
+
+    impl Output {
+        pub fn open() -> Result<Output, String> {
+            let (stream, handle) = OutputStream::try_default().map_err(|err| err.to_string())?;
+            Ok(Output {
+                _stream: stream,
+                handle,
+                music: None,
+            })
+        }
+
+        pub fn play(&self, notes: &'static [Note], volume: f32) {
+            let _ = self.handle.play_raw(tones(notes, volume));
+        }
+
+        pub fn start_music(&mut self, notes: &'static [Note], volume: f32) {
+            if let Ok(sink) = Sink::try_new(&self.handle) {
+                sink.append(tones(notes, 1.0).repeat_infinite());
+                sink.set_volume(volume);
+                self.music = Some(sink);
+            }
+        }
+
+        pub fn set_music_volume(&self, volume: f32) {
+            if let Some(music) = &self.music {
+                music.set_volume(volume);
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "audio"))]
+mod backend {
+    use super::Note;
+
+    pub static ENABLED: bool = false;
+
+    /// Never constructed: without the `audio` feature there is no output.
+    pub enum Output {}
+
+    impl Output {
+        pub fn open() -> Result<Output, String> {
+            Err(String::from("built without the audio feature"))
+        }
+
+        pub fn play(&self, _notes: &'static [Note], _volume: f32) {
+            match *self {}
+        }
+
+        pub fn start_music(&mut self, _notes: &'static [Note], _volume: f32) {
+            match *self {}
+        }
+
+        pub fn set_music_volume(&self, _volume: f32) {
+            match *self {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rust_snake::game::Direction;
+
+    #[test]
+    fn every_event_has_a_sound() {
+        assert_eq!(
+            sound_for(&GameEvent::AteFood {
+                snake: 0,
+                kind: FoodKind::Normal
+            }),
+            Sound::Eat
+        );
+        assert_eq!(
+            sound_for(&GameEvent::AteFood {
+                snake: 0,
+                kind: FoodKind::SpeedBoost
+            }),
+            Sound::SpeedUp
+        );
+        assert_eq!(
+            sound_for(&GameEvent::Turned {
+                snake: 1,
+                direction: Direction::Up
+            }),
+            Sound::Turn
+        );
+        assert_eq!(sound_for(&GameEvent::Won { snake: 0 }), Sound::Victory);
+        for sound in [
+            Sound::Eat,
+            Sound::Bonus,
+            Sound::Shrink,
+            Sound::SpeedUp,
+            Sound::SlowDown,
+            Sound::PowerUp,
+            Sound::Turn,
+            Sound::Death,
+            Sound::Victory,
+        ] {
+            assert!(!notes(sound).is_empty());
+        }
+    }
+
+    #[test]
+    fn mute_silences_effects_and_music() {
+        let mut settings = AudioSettings::default();
+        assert_eq!(settings.effects_volume(), 0.7);
+        assert!(settings.music_volume() > 0.0);
+        settings.music = false;
+        assert_eq!(settings.music_volume(), 0.0);
+        settings.music = true;
+        settings.muted = true;
+        assert_eq!(settings.effects_volume(), 0.0);
+        assert_eq!(settings.music_volume(), 0.0);
+    }
+}
@@ -0,0 +1,149 @@
+//! The daily challenge: one level per day, the same for everyone, derived
+//! from the date alone so no server is needed to share it.
+
+use crate::game::Position;
+use crate::level::Level;
+use crate::obstacle::Obstacle;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static SECONDS_PER_DAY: u64 = 86_400;
+/// Attempts at placing blocks; ones that would cut off part of the board are
+/// skipped, so a level can end up with fewer.
+static BLOCK_ATTEMPTS_PER_CELL: usize = 20;
+
+/// A calendar day in UTC, so the challenge changes at the same moment
+/// everywhere.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    pub fn today() -> Date {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        Date::from_days((seconds / SECONDS_PER_DAY) as i64)
+    }
+
+    /// The date `days` days after 1970-01-01.
+    pub fn from_days(days: i64) -> Date {
+        // Howard Hinnant's civil_from_days, with eras of 400 years that start
+        // on March 1st so leap days fall at the end.
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        } as u32;
+        let year = (year_of_era + era * 400 + i64::from(month <= 2)) as i32;
+        Date { year, month, day }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.year as u64 * 10_000 + self.month as u64 * 100 + self.day as u64
+    }
+}
+
+/// Formats as `YYYY-MM-DD`, which is also the key in the high-score file.
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// The level for `date`: its board size, obstacles and food seed all come
+/// from the date. The top and bottom rows stay clear for the snakes to start
+/// on and the open cells stay connected.
+pub fn daily_level(date: Date) -> Level {
+    let mut rng = ChaCha8Rng::seed_from_u64(date.seed());
+    let rows = rng.gen_range(10..=16);
+    let cols = rng.gen_range(10..=20);
+    let mut level = Level {
+        name: format!("Daily {date}"),
+        rows,
+        cols,
+        seed: Some(rng.gen()),
+        ..Level::default()
+    };
+
+    let blocks = rows * cols / 20;
+    let mut placed = 0;
+    for _ in 0..blocks * BLOCK_ATTEMPTS_PER_CELL {
+        if placed == blocks {
+            break;
+        }
+        let position = Position::new(
+            rng.gen_range(2..rows as i32 - 2),
+            rng.gen_range(0..cols as i32),
+        );
+        let block = Obstacle::Block { position };
+        if level.obstacles.contains(&block) {
+            continue;
+        }
+        level.obstacles.push(block);
+        if level.open_cells_connected() {
+            placed += 1;
+        } else {
+            level.obstacles.pop();
+        }
+    }
+
+    // A bar sweeping around somewhere in the middle, clear of the blocks.
+    let pivot = Position::new(
+        rng.gen_range(3..rows as i32 - 3),
+        rng.gen_range(2..cols as i32 - 2),
+    );
+    let bar = Obstacle::Rotating {
+        pivot,
+        length: 1,
+        period: rng.gen_range(2..=4),
+    };
+    let sweep = bar.reach();
+    level
+        .obstacles
+        .retain(|obstacle| obstacle.reach().iter().all(|cell| !sweep.contains(cell)));
+    level.obstacles.push(bar);
+    level
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn from_days_handles_leap_years() {
+        assert_eq!(Date::from_days(0).to_string(), "1970-01-01");
+        assert_eq!(Date::from_days(11_016).to_string(), "2000-02-29");
+        assert_eq!(Date::from_days(20_744).to_string(), "2026-10-18");
+        assert_eq!(Date::from_days(-1).to_string(), "1969-12-31");
+    }
+
+    #[test]
+    fn daily_level_depends_only_on_date() {
+        let date = Date::from_days(20_744);
+        let level = daily_level(date);
+        assert_eq!(level, daily_level(date));
+        assert_ne!(level, daily_level(Date::from_days(20_745)));
+        assert!(level.open_cells_connected());
+        assert!(level.seed.is_some());
+        for obstacle in &level.obstacles {
+            for cell in obstacle.reach() {
+                assert!(cell.get_row() >= 2 && cell.get_row() < level.rows as i32 - 2);
+            }
+        }
+    }
+}
@@ -0,0 +1,416 @@
+use crate::game::{Direction, Game, GameSettings, Position};
+use std::collections::VecDeque;
+
+/// The moves an agent can make. Reversing into the snake's own neck is
+/// ignored by the game, just like for a human player.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Action {
+    Left,
+    Up,
+    Right,
+    Down,
+}
+
+impl From<Action> for Direction {
+    fn from(action: Action) -> Direction {
+        match action {
+            Action::Left => Direction::Left,
+            Action::Up => Direction::Up,
+            Action::Right => Direction::Right,
+            Action::Down => Direction::Down,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct RewardConfig {
+    pub food: f32,
+    pub death: f32,
+    pub step: f32,
+}
+
+impl Default for RewardConfig {
+    fn default() -> Self {
+        RewardConfig {
+            food: 1.0,
+            death: -1.0,
+            step: -0.01,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ObservationKind {
+    /// Whole board as three channels (body, head, food), shape `[3, rows, cols]`.
+    Grid,
+    /// A square window around the head, rotated so the snake always faces up.
+    /// Two channels (danger, food), shape `[2, 2 * radius + 1, 2 * radius + 1]`.
+    Egocentric { radius: usize },
+    /// Danger ahead/left/right, heading one-hot and food up/down/left/right, shape `[11]`.
+    Features,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Observation {
+    pub shape: Vec<usize>,
+    pub data: Vec<f32>,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct StepInfo {
+    pub score: u32,
+    pub length: usize,
+    pub steps: u32,
+    pub cause: Option<&'static str>,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct EnvConfig {
+    pub num_rows: usize,
+    pub num_cols: usize,
+    pub rewards: RewardConfig,
+    pub observation: ObservationKind,
+    /// Ends the episode after this many steps, if set.
+    pub max_steps: Option<u32>,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        EnvConfig {
+            num_rows: 10,
+            num_cols: 10,
+            rewards: RewardConfig::default(),
+            observation: ObservationKind::Grid,
+            max_steps: None,
+        }
+    }
+}
+
+/// Headless, deterministic gym-style wrapper around `Game`.
+pub struct SnakeEnv {
+    config: EnvConfig,
+    game: Game,
+    steps: u32,
+    done: bool,
+}
+
+impl SnakeEnv {
+    pub fn new(config: EnvConfig) -> SnakeEnv {
+        let game = create_env_game(&config, 0);
+        SnakeEnv {
+            config,
+            game,
+            steps: 0,
+            done: false,
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Starts a new episode. The same seed always produces the same episode
+    /// for the same sequence of actions.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game = create_env_game(&self.config, seed);
+        self.steps = 0;
+        self.done = false;
+        self.observe()
+    }
+
+    pub fn step(&mut self, action: Action) -> (Observation, f32, bool, StepInfo) {
+        if self.done {
+            return (self.observe(), 0.0, true, self.info(None));
+        }
+
+        let old_score = self.game.get_score();
+        self.game.set_next_direction(action.into());
+        let result = self.game.update_game();
+        self.steps += 1;
+
+        let mut reward = self.config.rewards.step;
+        if self.game.get_score() > old_score {
+            reward += self.config.rewards.food;
+        }
+        let cause = result.err();
+        if cause.is_some() {
+            self.done = true;
+            if !self.board_is_full() {
+                reward += self.config.rewards.death;
+            }
+        }
+        if let Some(max_steps) = self.config.max_steps {
+            if self.steps >= max_steps {
+                self.done = true;
+            }
+        }
+
+        (self.observe(), reward, self.done, self.info(cause))
+    }
+
+    fn board_is_full(&self) -> bool {
+        self.game.get_snake_positions().len() == self.game.get_num_rows() * self.game.get_num_cols()
+    }
+
+    fn info(&self, cause: Option<&'static str>) -> StepInfo {
+        StepInfo {
+            score: self.game.get_score(),
+            length: self.game.get_snake_positions().len(),
+            steps: self.steps,
+            cause,
+        }
+    }
+
+    pub fn observe(&self) -> Observation {
+        match self.config.observation {
+            ObservationKind::Grid => self.observe_grid(),
+            ObservationKind::Egocentric { radius } => self.observe_egocentric(radius),
+            ObservationKind::Features => self.observe_features(),
+        }
+    }
+
+    fn observe_grid(&self) -> Observation {
+        let num_rows = self.game.get_num_rows();
+        let num_cols = self.game.get_num_cols();
+        let plane = num_rows * num_cols;
+        let mut data = vec![0.0; 3 * plane];
+        let mut positions = self.game.get_snake_positions().iter();
+        let index = |position: &Position| {
+            position.get_row() as usize * num_cols + position.get_column() as usize
+        };
+
+        if let Some(head) = positions.next() {
+            if in_bounds(&self.game, *head) {
+                data[plane + index(head)] = 1.0;
+            }
+        }
+        for position in positions {
+            data[index(position)] = 1.0;
+        }
+        for food in self.game.get_food() {
+            data[2 * plane + index(food.get_position())] = 1.0;
+        }
+
+        Observation {
+            shape: vec![3, num_rows, num_cols],
+            data,
+        }
+    }
+
+    fn observe_egocentric(&self, radius: usize) -> Observation {
+        let side = 2 * radius + 1;
+        let plane = side * side;
+        let mut data = vec![0.0; 2 * plane];
+        let head = *self.game.get_snake_positions().front().unwrap();
+        let (forward, right) = heading_axes(self.game.get_current_direction());
+
+        for i in 0..side {
+            for j in 0..side {
+                let ahead = radius as i32 - i as i32;
+                let side_offset = j as i32 - radius as i32;
+                let position = Position::new(
+                    head.get_row() + ahead * forward.0 + side_offset * right.0,
+                    head.get_column() + ahead * forward.1 + side_offset * right.1,
+                );
+                if position != head && !self.game.is_free(position) {
+                    data[i * side + j] = 1.0;
+                }
+                if self.game.food_at(position).is_some() {
+                    data[plane + i * side + j] = 1.0;
+                }
+            }
+        }
+
+        Observation {
+            shape: vec![2, side, side],
+            data,
+        }
+    }
+
+    fn observe_features(&self) -> Observation {
+        let head = *self.game.get_snake_positions().front().unwrap();
+        let direction = self.game.get_current_direction();
+        let (forward, right) = heading_axes(direction);
+        let danger = |offset: (i32, i32)| {
+            let position = Position::new(head.get_row() + offset.0, head.get_column() + offset.1);
+            if self.game.is_free(position) {
+                0.0
+            } else {
+                1.0
+            }
+        };
+        // With several items only the oldest is described; after the board
+        // fills up there is none.
+        let food = self.game.get_food_position().copied().unwrap_or(head);
+        let flag = |condition: bool| if condition { 1.0 } else { 0.0 };
+
+        let data = vec![
+            danger(forward),
+            danger((-right.0, -right.1)),
+            danger(right),
+            flag(direction == Direction::Left),
+            flag(direction == Direction::Up),
+            flag(direction == Direction::Right),
+            flag(direction == Direction::Down),
+            flag(food.get_row() < head.get_row()),
+            flag(food.get_row() > head.get_row()),
+            flag(food.get_column() < head.get_column()),
+            flag(food.get_column() > head.get_column()),
+        ];
+
+        Observation {
+            shape: vec![data.len()],
+            data,
+        }
+    }
+}
+
+fn in_bounds(game: &Game, position: Position) -> bool {
+    position.get_row() >= 0
+        && (position.get_row() as usize) < game.get_num_rows()
+        && position.get_column() >= 0
+        && (position.get_column() as usize) < game.get_num_cols()
+}
+
+/// Row/column offsets pointing ahead of and to the right of the snake.
+fn heading_axes(direction: Direction) -> ((i32, i32), (i32, i32)) {
+    match direction {
+        Direction::Left => ((0, -1), (-1, 0)),
+        Direction::Up => ((-1, 0), (0, 1)),
+        Direction::Right => ((0, 1), (1, 0)),
+        Direction::Down => ((1, 0), (0, -1)),
+        Direction::UpLeft => ((-1, -1), (-1, 1)),
+        Direction::UpRight => ((-1, 1), (1, 1)),
+        Direction::DownRight => ((1, 1), (1, -1)),
+        Direction::DownLeft => ((1, -1), (-1, -1)),
+    }
+}
+
+fn create_env_game(config: &EnvConfig, seed: u64) -> Game {
+    let num_rows = config.num_rows.max(1);
+    let num_cols = config.num_cols.max(3);
+    let row = (num_rows / 2) as i32;
+    let mut snake_body = VecDeque::new();
+    for column in 0..3 {
+        snake_body.push_front(Position::new(row, column));
+    }
+
+    let mut game = Game::new(
+        num_rows,
+        num_cols,
+        snake_body,
+        Direction::Right,
+        Position::new(0, num_cols as i32 - 1),
+    );
+    game.set_seed(seed);
+    // Timed and special items would make rewards depend on more than the
+    // agent's moves, so episodes only use regular food.
+    game.set_settings(GameSettings {
+        special_food: false,
+        ..GameSettings::default()
+    });
+    // Only fails when the snake already fills the board, in which case the
+    // placeholder food is left where it is.
+    let _ = game.spawn_new_food();
+    game
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn play(env: &mut SnakeEnv, actions: &[Action]) -> Vec<(Observation, f32, bool, StepInfo)> {
+        actions.iter().map(|action| env.step(*action)).collect()
+    }
+
+    #[test]
+    fn reset_is_deterministic() {
+        let actions = [Action::Right, Action::Down, Action::Down, Action::Left];
+        let mut first = SnakeEnv::new(EnvConfig::default());
+        let mut second = SnakeEnv::new(EnvConfig::default());
+
+        assert_eq!(first.reset(7), second.reset(7));
+        assert_eq!(play(&mut first, &actions), play(&mut second, &actions));
+    }
+
+    #[test]
+    fn grid_observation_shape() {
+        let mut env = SnakeEnv::new(EnvConfig {
+            num_rows: 6,
+            num_cols: 8,
+            ..EnvConfig::default()
+        });
+        let observation = env.reset(1);
+        assert_eq!(observation.shape, vec![3, 6, 8]);
+        assert_eq!(observation.data.iter().sum::<f32>(), 4.0);
+    }
+
+    #[test]
+    fn egocentric_observation_sees_wall_ahead() {
+        let mut env = SnakeEnv::new(EnvConfig {
+            num_rows: 1,
+            num_cols: 3,
+            observation: ObservationKind::Egocentric { radius: 2 },
+            ..EnvConfig::default()
+        });
+        let observation = env.reset(0);
+
+        assert_eq!(observation.shape, vec![2, 5, 5]);
+        // The head is at the last column, so the cell straight ahead is a wall.
+        assert_eq!(observation.data[5 + 2], 1.0);
+    }
+
+    #[test]
+    fn features_observation_shape() {
+        let mut env = SnakeEnv::new(EnvConfig {
+            observation: ObservationKind::Features,
+            ..EnvConfig::default()
+        });
+        let observation = env.reset(3);
+        assert_eq!(observation.shape, vec![11]);
+        assert_eq!(observation.data[5], 1.0);
+    }
+
+    #[test]
+    fn step_rewards_death() {
+        let mut env = SnakeEnv::new(EnvConfig::default());
+        env.reset(0);
+        let mut last = env.step(Action::Up);
+        while !last.2 {
+            last = env.step(Action::Up);
+        }
+        let rewards = RewardConfig::default();
+        assert_eq!(last.1, rewards.step + rewards.death);
+        assert!(last.3.cause.is_some());
+    }
+
+    #[test]
+    fn step_rewards_food() {
+        let mut env = SnakeEnv::new(EnvConfig {
+            num_rows: 1,
+            num_cols: 4,
+            ..EnvConfig::default()
+        });
+        env.reset(0);
+        let (_, reward, done, info) = env.step(Action::Right);
+        let rewards = RewardConfig::default();
+        assert_eq!(reward, rewards.step + rewards.food);
+        assert!(done);
+        assert_eq!(info.score, 1);
+        assert_eq!(info.length, 4);
+    }
+
+    #[test]
+    fn max_steps_ends_episode() {
+        let mut env = SnakeEnv::new(EnvConfig {
+            num_rows: 20,
+            num_cols: 20,
+            max_steps: Some(2),
+            ..EnvConfig::default()
+        });
+        env.reset(0);
+        assert!(!env.step(Action::Down).2);
+        assert!(env.step(Action::Down).2);
+    }
+}
@@ -0,0 +1,116 @@
+use piston_window::math::Matrix2d;
+use piston_window::{rectangle, G2d, Transformed};
+
+/// Glyphs are 3 pixels wide and 5 tall, with one pixel of spacing.
+pub static GLYPH_WIDTH: f64 = 3.0;
+pub static GLYPH_HEIGHT: f64 = 5.0;
+static GLYPH_ADVANCE: f64 = 4.0;
+
+/// Each row is three bits, most significant bit on the left.
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '?' => [0b110, 0b001, 0b010, 0b000, 0b010],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        _ => [0; 5],
+    }
+}
+
+/// Width of `text` drawn with glyph pixels of `pixel` size.
+pub fn text_width(text: &str, pixel: f64) -> f64 {
+    let count = text.chars().count() as f64;
+    if count == 0.0 {
+        return 0.0;
+    }
+    (count * GLYPH_ADVANCE - (GLYPH_ADVANCE - GLYPH_WIDTH)) * pixel
+}
+
+/// Draws `text` with its top-left corner at `x`, `y` using the built-in
+/// bitmap font, so no font file has to ship with the game.
+pub fn draw_text(
+    text: &str,
+    x: f64,
+    y: f64,
+    pixel: f64,
+    color: [f32; 4],
+    transform: Matrix2d,
+    g: &mut G2d,
+) {
+    let transform = transform.trans(x, y);
+    for (index, c) in text.chars().enumerate() {
+        let left = index as f64 * GLYPH_ADVANCE * pixel;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) != 0 {
+                    rectangle(
+                        color,
+                        [
+                            left + column as f64 * pixel,
+                            row as f64 * pixel,
+                            pixel,
+                            pixel,
+                        ],
+                        transform,
+                        g,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Draws `text` horizontally centered on `center_x`.
+pub fn draw_text_centered(
+    text: &str,
+    center_x: f64,
+    y: f64,
+    pixel: f64,
+    color: [f32; 4],
+    transform: Matrix2d,
+    g: &mut G2d,
+) {
+    let x = center_x - text_width(text, pixel) / 2.0;
+    draw_text(text, x, y, pixel, color, transform, g);
+}
@@ -1,16 +1,281 @@
-use piston_window::Key;
-use rand::distributions::{Distribution, Uniform};
+use rand::distributions::{Distribution, Uniform, WeightedIndex};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
-#[derive(PartialEq, Debug)]
+use crate::mode::GameMode;
+use crate::obstacle::Obstacle;
+use crate::topology::{Grid, Topology};
+
+/// Length of a tick at normal speed. Frontends divide it by `get_speed`.
+pub static TICK_MILLIS: u64 = 250;
+static DEFAULT_SEED: u64 = 0;
+static MAX_QUEUED_INPUTS: usize = 3;
+static GOLDEN_POINTS: u32 = 5;
+/// Segments a shrink item removes on top of the usual tail move.
+static SHRINK_SEGMENTS: usize = 2;
+static SPEED_EFFECT_TICKS: u32 = 40;
+static SPEED_BOOST: f64 = 1.5;
+static SLOW_MOTION: f64 = 0.6;
+/// Items this close to expiring are flagged so frontends can warn about them.
+static EXPIRY_WARNING_TICKS: u32 = 8;
+/// Food within this many steps of a magnet's head drifts toward it.
+static MAGNET_RADIUS: i32 = 4;
+
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Direction {
     Left,
     Up,
     Right,
     Down,
+    /// Diagonals, for grids that allow them; see `topology`.
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
 }
 
+impl Direction {
+    pub fn is_diagonal(self) -> bool {
+        matches!(
+            self,
+            Direction::UpLeft | Direction::UpRight | Direction::DownLeft | Direction::DownRight
+        )
+    }
+
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Up => Direction::Down,
+            Direction::Right => Direction::Left,
+            Direction::Down => Direction::Up,
+            Direction::UpLeft => Direction::DownRight,
+            Direction::UpRight => Direction::DownLeft,
+            Direction::DownLeft => Direction::UpRight,
+            Direction::DownRight => Direction::UpLeft,
+        }
+    }
+}
+
+/// A turn relative to the way a snake is heading, for two-button controls.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Turn {
+    Left,
+    Right,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum FoodKind {
+    /// Grows the snake by one and scores a point.
+    Normal,
+    /// Scores bonus points but disappears after a short while.
+    Golden,
+    /// Shortens the snake instead of growing it.
+    Shrink,
+    /// Speeds the game up for a while.
+    SpeedBoost,
+    /// Slows the game down for a while.
+    SlowMotion,
+    /// Grants `PowerUp::Ghost`.
+    Ghost,
+    /// Grants `PowerUp::Shield`.
+    Shield,
+    /// Grants `PowerUp::Magnet`.
+    Magnet,
+}
+
+impl FoodKind {
+    pub const ALL: [FoodKind; 8] = [
+        FoodKind::Normal,
+        FoodKind::Golden,
+        FoodKind::Shrink,
+        FoodKind::SpeedBoost,
+        FoodKind::SlowMotion,
+        FoodKind::Ghost,
+        FoodKind::Shield,
+        FoodKind::Magnet,
+    ];
+
+    /// Relative chance of spawning this kind.
+    pub fn spawn_weight(self) -> u32 {
+        match self {
+            FoodKind::Normal => 70,
+            FoodKind::Golden => 10,
+            FoodKind::Shrink => 8,
+            FoodKind::SpeedBoost => 6,
+            FoodKind::SlowMotion => 6,
+            FoodKind::Ghost => 4,
+            FoodKind::Shield => 4,
+            FoodKind::Magnet => 4,
+        }
+    }
+
+    /// Ticks before an uneaten item disappears; regular food stays forever.
+    pub fn lifetime(self) -> Option<u32> {
+        match self {
+            FoodKind::Normal => None,
+            FoodKind::Golden => Some(25),
+            _ => Some(60),
+        }
+    }
+
+    /// The power-up eating this kind grants, if any.
+    pub fn power_up(self) -> Option<PowerUp> {
+        match self {
+            FoodKind::Ghost => Some(PowerUp::Ghost),
+            FoodKind::Shield => Some(PowerUp::Shield),
+            FoodKind::Magnet => Some(PowerUp::Magnet),
+            _ => None,
+        }
+    }
+
+    pub fn points(self) -> u32 {
+        match self {
+            FoodKind::Golden => GOLDEN_POINTS,
+            _ => 1,
+        }
+    }
+}
+
+/// Timed effect on a single snake that bends the rules for it.
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum PowerUp {
+    /// The head passes through the snake's own body.
+    Ghost,
+    /// Absorbs one wall hit; the snake stays put for that tick instead.
+    Shield,
+    /// Pulls nearby food toward the head.
+    Magnet,
+}
+
+impl PowerUp {
+    /// Ticks the effect lasts. A shield also ends when it absorbs a hit.
+    pub fn duration(self) -> u32 {
+        match self {
+            PowerUp::Ghost => 30,
+            PowerUp::Shield => 80,
+            PowerUp::Magnet => 50,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ActivePowerUp {
+    pub power_up: PowerUp,
+    pub ticks_left: u32,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Food {
+    position: Position,
+    kind: FoodKind,
+    ticks_left: Option<u32>,
+}
+
+impl Food {
+    pub fn new(position: Position, kind: FoodKind) -> Food {
+        Food {
+            position,
+            kind,
+            ticks_left: kind.lifetime(),
+        }
+    }
+
+    pub fn get_position(&self) -> &Position {
+        &self.position
+    }
+
+    pub fn get_kind(&self) -> FoodKind {
+        self.kind
+    }
+
+    /// Ticks until the item disappears, if it expires at all.
+    pub fn get_ticks_left(&self) -> Option<u32> {
+        self.ticks_left
+    }
+
+    pub fn is_expiring(&self) -> bool {
+        self.ticks_left
+            .is_some_and(|ticks_left| ticks_left <= EXPIRY_WARNING_TICKS)
+    }
+}
+
+/// Rules that vary between games. The defaults are the classic game, and
+/// settings an older save leaves out keep them.
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameSettings {
+    /// How many food items are on the board at once.
+    pub max_food: usize,
+    /// Whether golden, shrink and speed items spawn. Regular food always does.
+    pub special_food: bool,
+    /// Segments a snake grows per item eaten, added one per tick.
+    pub growth_per_food: usize,
+    /// Length of the snakes laid out at the start, see `Snake::with_length`.
+    pub start_length: usize,
+    /// Snakes stop growing at this length.
+    pub max_length: Option<usize>,
+    pub mode: GameMode,
+    pub grid: Grid,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        GameSettings {
+            max_food: 1,
+            special_food: true,
+            growth_per_food: 1,
+            start_length: 3,
+            max_length: None,
+            mode: GameMode::Classic,
+            grid: Grid::Square,
+        }
+    }
+}
+
+/// What a HUD needs to know about one snake.
+#[derive(PartialEq, Debug, Clone)]
+pub struct SnakeView {
+    pub score: u32,
+    pub alive: bool,
+    pub length: usize,
+    pub power_ups: Vec<ActivePowerUp>,
+}
+
+/// Read-only summary of a game for HUDs and other displays that don't need
+/// the board itself.
+#[derive(PartialEq, Debug, Clone)]
+pub struct GameView {
+    pub snakes: Vec<SnakeView>,
+    /// See `Game::get_speed`.
+    pub speed: f64,
+}
+
+/// Something that happened during the last tick, for frontends that want to
+/// react to it (e.g. with sound) without diffing the whole game state.
 #[derive(PartialEq, Debug, Clone, Copy)]
+pub enum GameEvent {
+    AteFood {
+        snake: usize,
+        kind: FoodKind,
+    },
+    Turned {
+        snake: usize,
+        direction: Direction,
+    },
+    Died {
+        snake: usize,
+        cause: &'static str,
+    },
+    /// The round was won: by the last snake standing in a multiplayer game,
+    /// or by filling the whole board in a single-player one.
+    Won {
+        snake: usize,
+    },
+}
+
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Position {
     row: i32,
     column: i32,
@@ -28,38 +293,268 @@ impl Position {
     pub fn get_column(&self) -> i32 {
         self.column
     }
+
+    /// The neighbour in `direction` on a square grid, diagonals included.
+    /// Other grids step through their `Topology`.
+    pub fn step(&self, direction: Direction) -> Position {
+        match direction {
+            Direction::Left => Position::new(self.row, self.column - 1),
+            Direction::Up => Position::new(self.row - 1, self.column),
+            Direction::Right => Position::new(self.row, self.column + 1),
+            Direction::Down => Position::new(self.row + 1, self.column),
+            Direction::UpLeft => Position::new(self.row - 1, self.column - 1),
+            Direction::UpRight => Position::new(self.row - 1, self.column + 1),
+            Direction::DownLeft => Position::new(self.row + 1, self.column - 1),
+            Direction::DownRight => Position::new(self.row + 1, self.column + 1),
+        }
+    }
+}
+
+/// Two linked cells. A head stepping onto either end comes out of the other
+/// one and carries on in the same direction, so portal cells themselves are
+/// never occupied.
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Portal {
+    pub a: Position,
+    pub b: Position,
+}
+
+impl Portal {
+    /// The other end, if `position` is one end of this portal.
+    pub fn exit(&self, position: Position) -> Option<Position> {
+        if position == self.a {
+            Some(self.b)
+        } else if position == self.b {
+            Some(self.a)
+        } else {
+            None
+        }
+    }
+}
+
+/// Where a step in `direction` from `position` leads on `topology`, taking
+/// `portals` into account. Portals don't chain: the cell beyond an exit is
+/// used as is.
+pub fn step_through(
+    topology: &dyn Topology,
+    portals: &[Portal],
+    position: Position,
+    direction: Direction,
+) -> Position {
+    let next = topology.step(position, direction);
+    match portals.iter().find_map(|portal| portal.exit(next)) {
+        Some(exit) => topology.step(exit, direction),
+        None => next,
+    }
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Snake {
+    body: VecDeque<Position>,
+    direction: Direction,
+    input_queue: VecDeque<Direction>,
+    score: u32,
+    alive: bool,
+    power_ups: Vec<ActivePowerUp>,
+    /// Segments still to be added, one per tick.
+    pending_growth: usize,
+    /// Ticks since the snake last ate.
+    #[serde(default)]
+    hunger: u32,
+}
+
+impl Snake {
+    pub fn new(body: VecDeque<Position>, direction: Direction) -> Snake {
+        Snake {
+            body,
+            direction,
+            input_queue: VecDeque::new(),
+            score: 0,
+            alive: true,
+            power_ups: Vec::new(),
+            pending_growth: 0,
+            hunger: 0,
+        }
+    }
+
+    /// A straight snake of `length` segments (at least one) with its head at
+    /// `head`, heading `direction`; the body trails behind it.
+    pub fn with_length(head: Position, direction: Direction, length: usize) -> Snake {
+        let mut body = VecDeque::new();
+        let mut position = head;
+        for _ in 0..length.max(1) {
+            body.push_back(position);
+            position = position.step(direction.opposite());
+        }
+        Snake::new(body, direction)
+    }
+
+    /// Rebuilds a snake from state received from elsewhere, e.g. a network server.
+    pub fn restore(
+        body: VecDeque<Position>,
+        direction: Direction,
+        score: u32,
+        alive: bool,
+        power_ups: Vec<ActivePowerUp>,
+    ) -> Snake {
+        Snake {
+            score,
+            alive,
+            power_ups,
+            ..Snake::new(body, direction)
+        }
+    }
+
+    pub fn get_power_ups(&self) -> &[ActivePowerUp] {
+        &self.power_ups
+    }
+
+    pub fn has_power_up(&self, power_up: PowerUp) -> bool {
+        self.power_ups
+            .iter()
+            .any(|active| active.power_up == power_up)
+    }
+
+    /// Starts `power_up`, or restarts its timer if it is already active.
+    fn grant_power_up(&mut self, power_up: PowerUp) {
+        self.power_ups.retain(|active| active.power_up != power_up);
+        self.power_ups.push(ActivePowerUp {
+            power_up,
+            ticks_left: power_up.duration(),
+        });
+    }
+
+    fn end_power_up(&mut self, power_up: PowerUp) {
+        self.power_ups.retain(|active| active.power_up != power_up);
+    }
+
+    pub fn get_body(&self) -> &VecDeque<Position> {
+        &self.body
+    }
+
+    pub fn get_direction(&self) -> Direction {
+        self.direction
+    }
+
+    pub fn get_score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.alive
+    }
+
+    /// Buffers a turn so several quick key presses are applied on consecutive ticks
+    /// instead of only the last one counting.
+    fn queue_direction(&mut self, direction: Direction) {
+        if self.input_queue.len() < MAX_QUEUED_INPUTS {
+            self.input_queue.push_back(direction);
+        }
+    }
+
+    /// The heading the snake will have once the turns already queued are
+    /// applied. New turns are taken from it, so pressing right twice quickly
+    /// makes a U-turn.
+    pub fn queued_heading(&self) -> Direction {
+        self.input_queue.back().copied().unwrap_or(self.direction)
+    }
+
+    /// Applies the next queued turn. Returns true if the direction changed.
+    fn update_direction(&mut self) -> bool {
+        let previous = self.direction;
+        match self.input_queue.pop_front() {
+            Some(direction) if direction != self.direction.opposite() => self.direction = direction,
+            _ => (),
+        }
+        self.direction != previous
+    }
+}
+
+#[derive(PartialEq, Debug, Serialize, Deserialize)]
 pub struct Game {
     num_rows: usize,
     num_cols: usize,
     board: Vec<Vec<bool>>,
-    snake_body: VecDeque<Position>,
-    current_snake_direction: Direction,
-    next_snake_position: Option<Direction>,
-    food_position: Position,
+    snakes: Vec<Snake>,
+    /// Items in the order they spawned.
+    #[serde(alias = "food_position", deserialize_with = "deserialize_food")]
+    food: Vec<Food>,
+    #[serde(default)]
+    settings: GameSettings,
+    /// Multiplier for how fast the game should tick, changed by speed items.
+    #[serde(default = "normal_speed")]
+    speed: f64,
+    #[serde(default)]
+    speed_ticks_left: u32,
+    /// Hazards on the board. Where the moving ones are depends on `ticks`.
+    #[serde(default)]
+    obstacles: Vec<Obstacle>,
+    /// Ticks played so far.
+    #[serde(default)]
+    ticks: u64,
+    #[serde(default)]
+    portals: Vec<Portal>,
+    /// Game time played so far, which runs faster or slower with the speed.
+    #[serde(default)]
+    elapsed_ms: u64,
+    rng: ChaCha8Rng,
+    /// Events of the most recent tick only; they are not part of the saved state.
+    #[serde(skip)]
+    events: Vec<GameEvent>,
+}
+
+/// Food as saves have held it over time: a plain position, one item, or the
+/// list of items.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedFood {
+    Items(Vec<Food>),
+    Item(Food),
+    Position(Position),
+}
+
+fn deserialize_food<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Food>, D::Error> {
+    Ok(match SavedFood::deserialize(deserializer)? {
+        SavedFood::Items(items) => items,
+        SavedFood::Item(item) => vec![item],
+        SavedFood::Position(position) => vec![Food::new(position, FoodKind::Normal)],
+    })
+}
+
+fn normal_speed() -> f64 {
+    1.0
 }
 
 impl Default for Game {
     fn default() -> Self {
         let num_rows = 10;
         let num_cols = 10;
-        let board = vec![vec![false; num_cols]; num_rows];
+        let mut board = vec![vec![false; num_cols]; num_rows];
         let mut snake_body = VecDeque::new();
         snake_body.push_front(Position::new(1, 1));
         snake_body.push_front(Position::new(2, 1));
-        let current_snake_direction = Direction::Right;
-        let next_snake_position = None;
+        for position in &snake_body {
+            board[position.row as usize][position.column as usize] = true;
+        }
+        let snakes = vec![Snake::new(snake_body, Direction::Right)];
         let food_position = Position::new(2, 2);
         Game {
             num_rows,
             num_cols,
             board,
-            snake_body,
-            current_snake_direction,
-            next_snake_position,
-            food_position,
+            snakes,
+            food: vec![Food::new(food_position, FoodKind::Normal)],
+            settings: GameSettings::default(),
+            speed: 1.0,
+            speed_ticks_left: 0,
+            obstacles: Vec::new(),
+            ticks: 0,
+            portals: Vec::new(),
+            elapsed_ms: 0,
+            rng: ChaCha8Rng::seed_from_u64(DEFAULT_SEED),
+            events: Vec::new(),
         }
     }
 }
@@ -68,9 +563,23 @@ impl Game {
     pub fn new(
         num_rows: usize,
         num_cols: usize,
-        mut snake_body: VecDeque<Position>,
+        snake_body: VecDeque<Position>,
         current_snake_direction: Direction,
         food_position: Position,
+    ) -> Game {
+        Game::new_multiplayer(
+            num_rows,
+            num_cols,
+            vec![Snake::new(snake_body, current_snake_direction)],
+            food_position,
+        )
+    }
+
+    pub fn new_multiplayer(
+        num_rows: usize,
+        num_cols: usize,
+        mut snakes: Vec<Snake>,
+        food_position: Position,
     ) -> Game {
         if num_rows == 0 {
             println!("num_rows is zero. Defaulting");
@@ -80,45 +589,68 @@ impl Game {
             println!("num_cols is zero. Defaulting");
             return Game::default();
         }
+        if snakes.is_empty() {
+            println!("No snakes. Defaulting");
+            return Game::default();
+        }
 
         let mut board = vec![vec![false; num_cols]; num_rows];
-        let mut previous_snake_position: Option<&Position> = None;
-        for snake_position in &snake_body {
-            if snake_position.row < 0
-                || snake_position.row as usize >= num_rows
-                || snake_position.column < 0
-                || snake_position.column as usize >= num_cols
-            {
-                println!("Snake is out of bounds. Defaulting");
-                return Game::default();
-            }
-            board[snake_position.row as usize][snake_position.column as usize] = true;
-            if let Some(old_position) = previous_snake_position {
-                if (snake_position.row == old_position.row) && (snake_position.column - old_position.column).abs() != 1 {
-                    println!("Snake is not contiguous column-wise. Defaulting");
-                    return Game::default();
-                } else if (snake_position.column == old_position.column)
-                    && (snake_position.row - old_position.row).abs() != 1
+        for snake in &snakes {
+            let mut previous_snake_position: Option<&Position> = None;
+            for snake_position in &snake.body {
+                if snake_position.row < 0
+                    || snake_position.row as usize >= num_rows
+                    || snake_position.column < 0
+                    || snake_position.column as usize >= num_cols
                 {
-                    println!("Snake is not contiguous row-wise. Defaulting");
+                    println!("Snake is out of bounds. Defaulting");
                     return Game::default();
-                } else if snake_position.column != old_position.column
-                    && snake_position.row != old_position.row
-                {
-                    println!("Snake differs in both row and column. Defaulting");
+                }
+                if board[snake_position.row as usize][snake_position.column as usize] {
+                    println!("Snakes overlap. Defaulting");
                     return Game::default();
                 }
-            }
+                board[snake_position.row as usize][snake_position.column as usize] = true;
+                if let Some(old_position) = previous_snake_position {
+                    if (snake_position.row == old_position.row)
+                        && (snake_position.column - old_position.column).abs() != 1
+                    {
+                        println!("Snake is not contiguous column-wise. Defaulting");
+                        return Game::default();
+                    } else if (snake_position.column == old_position.column)
+                        && (snake_position.row - old_position.row).abs() != 1
+                    {
+                        println!("Snake is not contiguous row-wise. Defaulting");
+                        return Game::default();
+                    } else if snake_position.column != old_position.column
+                        && snake_position.row != old_position.row
+                    {
+                        println!("Snake differs in both row and column. Defaulting");
+                        return Game::default();
+                    }
+                }
 
-            previous_snake_position = Some(snake_position);
+                previous_snake_position = Some(snake_position);
+            }
         }
 
-        if snake_body.is_empty() {
+        for snake in snakes.iter_mut().filter(|snake| snake.body.is_empty()) {
             println!("Snake_body is empty. Creating snake of length 1");
-            if food_position == Position::new(0, 0) {
-                snake_body.push_front(Position::new(1, 1));
-            } else {
-                snake_body.push_front(Position::new(0, 0));
+            let start = (0..num_rows.min(num_cols) as i32)
+                .map(|i| Position::new(i, i))
+                .find(|position| {
+                    *position != food_position
+                        && !board[position.row as usize][position.column as usize]
+                });
+            match start {
+                Some(position) => {
+                    board[position.row as usize][position.column as usize] = true;
+                    snake.body.push_front(position);
+                }
+                None => {
+                    println!("No room for empty snake. Defaulting");
+                    return Game::default();
+                }
             }
         }
         if food_position.row < 0
@@ -130,16 +662,41 @@ impl Game {
             return Game::default();
         }
 
-        let next_snake_position = None;
         Game {
             num_rows,
             num_cols,
             board,
-            snake_body,
-            current_snake_direction,
-            next_snake_position,
-            food_position,
+            snakes,
+            food: vec![Food::new(food_position, FoodKind::Normal)],
+            settings: GameSettings::default(),
+            speed: 1.0,
+            speed_ticks_left: 0,
+            obstacles: Vec::new(),
+            ticks: 0,
+            portals: Vec::new(),
+            elapsed_ms: 0,
+            rng: ChaCha8Rng::seed_from_u64(DEFAULT_SEED),
+            events: Vec::new(),
+        }
+    }
+
+    /// Checks the invariants `new_multiplayer` guarantees, for games that were
+    /// deserialized rather than constructed.
+    pub fn is_consistent(&self) -> bool {
+        let board_matches = self.board.len() == self.num_rows
+            && self.board.iter().all(|row| row.len() == self.num_cols);
+        if !board_matches {
+            return false;
         }
+        let snakes_fit = !self.snakes.is_empty()
+            && self.snakes.iter().all(|snake| {
+                !snake.body.is_empty()
+                    && snake.body.iter().all(|position| {
+                        self.in_bounds(*position)
+                            && self.board[position.row as usize][position.column as usize]
+                    })
+            });
+        snakes_fit && self.food.iter().all(|food| self.in_bounds(food.position))
     }
 
     pub fn get_num_rows(&self) -> usize {
@@ -150,53 +707,233 @@ impl Game {
         self.num_cols
     }
 
+    /// Body of the first snake, which is the only one in single-player games.
     pub fn get_snake_positions(&self) -> &VecDeque<Position> {
-        &self.snake_body
+        &self.snakes[0].body
     }
 
-    pub fn get_food_position(&self) -> &Position {
-        &self.food_position
+    pub fn get_snakes(&self) -> &[Snake] {
+        &self.snakes
     }
 
-    pub fn handle_key(&mut self, key: Key) {
-        match key {
-            Key::Left => self.next_snake_position = Some(Direction::Left),
-            Key::Up => self.next_snake_position = Some(Direction::Up),
-            Key::Right => self.next_snake_position = Some(Direction::Right),
-            Key::Down => self.next_snake_position = Some(Direction::Down),
-            _ => (),
-        }
+    /// Position of the oldest food item, which is the only one unless
+    /// `max_food` was raised. There is none once the board is full.
+    pub fn get_food_position(&self) -> Option<&Position> {
+        self.food.first().map(|food| &food.position)
     }
 
-    fn update_direction(&mut self) {
-        match self.next_snake_position {
-            Some(Direction::Left) => {
-                if self.current_snake_direction != Direction::Right {
-                    self.current_snake_direction = Direction::Left
-                }
-            }
-            Some(Direction::Up) => {
-                if self.current_snake_direction != Direction::Down {
-                    self.current_snake_direction = Direction::Up
-                }
+    pub fn get_food(&self) -> &[Food] {
+        &self.food
+    }
+
+    /// Index of the food item at `position`, if there is one.
+    pub fn food_at(&self, position: Position) -> Option<usize> {
+        self.food.iter().position(|food| food.position == position)
+    }
+
+    /// Replaces the food, e.g. to mirror a game running elsewhere. Items off
+    /// the board are dropped.
+    pub fn set_food(&mut self, food: Vec<Food>) {
+        self.food = food
+            .into_iter()
+            .filter(|food| self.in_bounds(food.position))
+            .collect();
+    }
+
+    pub fn get_settings(&self) -> &GameSettings {
+        &self.settings
+    }
+
+    /// Applies `settings` and adds or removes food to match `max_food`, which
+    /// is at least 1.
+    pub fn set_settings(&mut self, settings: GameSettings) {
+        self.settings = GameSettings {
+            max_food: settings.max_food.max(1),
+            start_length: settings.start_length.max(1),
+            max_length: settings.max_length.map(|length| length.max(1)),
+            ..settings
+        };
+        self.food.truncate(self.settings.max_food);
+        self.fill_food();
+    }
+
+    pub fn get_obstacles(&self) -> &[Obstacle] {
+        &self.obstacles
+    }
+
+    /// Replaces the obstacles. Food anywhere an obstacle can reach is moved
+    /// elsewhere, so it never ends up under a hazard.
+    pub fn set_obstacles(&mut self, obstacles: Vec<Obstacle>) {
+        self.obstacles = obstacles;
+        let reach = self.obstacle_reach();
+        self.food.retain(|food| !reach.contains(&food.position));
+        self.fill_food();
+    }
+
+    /// Board cells the obstacles cover this tick.
+    pub fn get_obstacle_cells(&self) -> Vec<Position> {
+        self.obstacles
+            .iter()
+            .flat_map(|obstacle| obstacle.cells(self.ticks))
+            .filter(|position| self.in_bounds(*position))
+            .collect()
+    }
+
+    /// Every cell any obstacle can ever cover.
+    fn obstacle_reach(&self) -> Vec<Position> {
+        self.obstacles.iter().flat_map(Obstacle::reach).collect()
+    }
+
+    pub fn get_portals(&self) -> &[Portal] {
+        &self.portals
+    }
+
+    /// Replaces the portals. Portals with an end off the board are dropped,
+    /// and food on a portal cell is moved elsewhere.
+    pub fn set_portals(&mut self, portals: Vec<Portal>) {
+        self.portals = portals
+            .into_iter()
+            .filter(|portal| self.in_bounds(portal.a) && self.in_bounds(portal.b))
+            .collect();
+        let portals = &self.portals;
+        self.food.retain(|food| {
+            portals
+                .iter()
+                .all(|portal| portal.exit(food.position).is_none())
+        });
+        self.fill_food();
+    }
+
+    fn is_portal(&self, position: Position) -> bool {
+        self.portals
+            .iter()
+            .any(|portal| portal.exit(position).is_some())
+    }
+
+    /// Where a head at `position` heading `direction` ends up next tick.
+    pub fn next_position(&self, position: Position, direction: Direction) -> Position {
+        step_through(
+            self.settings.grid.topology(),
+            &self.portals,
+            position,
+            direction,
+        )
+    }
+
+    /// Replaces the snakes, e.g. to mirror a game running elsewhere. Unlike
+    /// `new_multiplayer` bodies need not be contiguous, since they may be
+    /// split across a portal; segments off the board are dropped.
+    pub fn set_snakes(&mut self, mut snakes: Vec<Snake>) {
+        self.board = vec![vec![false; self.num_cols]; self.num_rows];
+        for snake in &mut snakes {
+            snake.body.retain(|position| {
+                position.row >= 0
+                    && (position.row as usize) < self.num_rows
+                    && position.column >= 0
+                    && (position.column as usize) < self.num_cols
+            });
+            for position in &snake.body {
+                self.board[position.row as usize][position.column as usize] = true;
             }
-            Some(Direction::Right) => {
-                if self.current_snake_direction != Direction::Left {
-                    self.current_snake_direction = Direction::Right
-                }
+        }
+        snakes.retain(|snake| !snake.body.is_empty());
+        if !snakes.is_empty() {
+            self.snakes = snakes;
+        }
+    }
+
+    pub fn get_ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Game time played so far, which the time limit counts against.
+    pub fn get_elapsed_ms(&self) -> u64 {
+        self.elapsed_ms
+    }
+
+    /// Sets the game time played, e.g. to mirror a game running elsewhere.
+    pub fn set_elapsed_ms(&mut self, elapsed_ms: u64) {
+        self.elapsed_ms = elapsed_ms;
+    }
+
+    /// Game time left before the round ends, in modes with a time limit.
+    pub fn get_time_left_ms(&self) -> Option<u64> {
+        self.settings
+            .mode
+            .time_limit_ms()
+            .map(|limit| limit.saturating_sub(self.elapsed_ms))
+    }
+
+    /// How much faster than normal the game should currently tick; frontends
+    /// divide their tick interval by it.
+    pub fn get_speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn get_current_direction(&self) -> Direction {
+        self.snakes[0].direction
+    }
+
+    /// Combined score of all snakes.
+    pub fn get_score(&self) -> u32 {
+        self.snakes.iter().map(|snake| snake.score).sum()
+    }
+
+    /// Reseeds the random number generator used for food placement.
+    /// Two games with the same seed and the same inputs play out identically.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+    }
+
+    fn in_bounds(&self, position: Position) -> bool {
+        position.row >= 0
+            && (position.row as usize) < self.num_rows
+            && position.column >= 0
+            && (position.column as usize) < self.num_cols
+    }
+
+    /// Returns true if `position` is on the board and not covered by any snake,
+    /// obstacle or portal.
+    pub fn is_free(&self, position: Position) -> bool {
+        self.in_bounds(position)
+            && !self.board[position.row as usize][position.column as usize]
+            && !self.get_obstacle_cells().contains(&position)
+            && !self.is_portal(position)
+    }
+
+    pub fn set_next_direction(&mut self, direction: Direction) {
+        self.queue_direction(0, direction);
+    }
+
+    /// Queues a turn for the snake at index `snake`. Unknown snakes are
+    /// ignored, and the grid's topology maps `direction` onto the directions
+    /// it allows, dropping it if none fits.
+    pub fn queue_direction(&mut self, snake: usize, direction: Direction) {
+        let topology = self.settings.grid.topology();
+        if let Some(snake) = self.snakes.get_mut(snake) {
+            if let Some(direction) = topology.resolve(snake.queued_heading(), direction) {
+                snake.queue_direction(direction);
             }
-            Some(Direction::Down) => {
-                if self.current_snake_direction != Direction::Up {
-                    self.current_snake_direction = Direction::Down
-                }
+        }
+    }
+
+    /// What happened during the last call to `update_game`.
+    pub fn get_events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
+    fn update_direction(&mut self) {
+        for (index, snake) in self.snakes.iter_mut().enumerate() {
+            if snake.alive && snake.update_direction() {
+                self.events.push(GameEvent::Turned {
+                    snake: index,
+                    direction: snake.direction,
+                });
             }
-            _ => (),
         }
-        self.next_snake_position = None;
     }
 
-    pub fn check_if_hit_wall(&self) -> Result<(), &'static str> {
-        let head = self.snake_body.front().unwrap();
+    fn wall_collision(&self, head: &Position) -> Result<(), &'static str> {
         if head.row >= self.num_rows as i32 || head.row < 0 {
             return Err("Snake hit top or bottom wall");
         } else if head.column >= self.num_cols as i32 || head.column < 0 {
@@ -205,79 +942,426 @@ impl Game {
         Ok(())
     }
 
-    pub fn check_if_hit_snake(&self) -> Result<(), &'static str> {
-        let head = self.snake_body.front().unwrap();
-        if self.board[head.row as usize][head.column as usize] == true {
-            return Err("Snake hit itself");
-        }
-
-        Ok(())
+    /// Index of the food item the first snake's head is on, if any.
+    pub fn snake_found_food(&self) -> Option<usize> {
+        self.food_at(*self.snakes[0].body.front().unwrap())
     }
 
-    pub fn snake_found_food(&self) -> bool {
-        self.snake_body.front().unwrap() == &self.food_position
-    }
-
-    fn spawn_new_food(&mut self) -> Result<(), &'static str> {
-        let mut rng = rand::thread_rng();
-
+    /// Places a new item on a cell without a snake, portal or other food that
+    /// no obstacle can reach. When the board already holds `max_food` items, the
+    /// oldest one is replaced.
+    pub fn spawn_new_food(&mut self) -> Result<(), &'static str> {
+        let replacing = self.food.len() >= self.settings.max_food;
+        let reach = self.obstacle_reach();
         let mut valid_new_position = Vec::new();
 
         for row in 0..self.board.len() {
             for col in 0..self.board[0].len() {
-                if self.board[row][col] == false {
-                    valid_new_position.push(Position::new(row as i32, col as i32));
+                let position = Position::new(row as i32, col as i32);
+                let food = self.food_at(position);
+                if !self.board[row][col]
+                    && !reach.contains(&position)
+                    && !self.is_portal(position)
+                    && (food.is_none() || (replacing && food == Some(0)))
+                {
+                    valid_new_position.push(position);
                 }
             }
         }
         if valid_new_position.is_empty() {
             return Err("No room to spawn food");
         }
+        if replacing {
+            self.food.remove(0);
+        }
 
         let rand_die = Uniform::from(0..valid_new_position.len());
-        let rand_index = rand_die.sample(&mut rng);
-        self.food_position = valid_new_position[rand_index];
+        let rand_index = rand_die.sample(&mut self.rng);
+        let kind = if self.settings.special_food {
+            let weights = FoodKind::ALL.map(FoodKind::spawn_weight);
+            let kinds = WeightedIndex::new(weights).unwrap();
+            FoodKind::ALL[kinds.sample(&mut self.rng)]
+        } else {
+            FoodKind::Normal
+        };
+        let mut food = Food::new(valid_new_position[rand_index], kind);
+        food.ticks_left = self.settings.mode.food_lifetime(kind);
+        self.food.push(food);
+        Ok(())
+    }
+
+    /// Spawns items until there are `max_food` of them or the board is full.
+    fn fill_food(&mut self) {
+        while self.food.len() < self.settings.max_food {
+            if self.spawn_new_food().is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Decides whether the snake at `index` survives moving its head to
+    /// `new_heads[index]`. All snakes move at the same time, so a snake dies when:
+    /// its new head leaves the board; two snakes swap head cells (head-on);
+    /// two new heads land on the same cell (head-to-head, both die); or the new
+    /// head lands on any cell covered by a snake before the move (head-to-body,
+    /// including tails that are about to move away); or the new head lands on
+    /// an obstacle or swaps cells with one. A ghost passes through its own body.
+    fn collision(&self, index: usize, new_heads: &[Option<Position>]) -> Result<(), &'static str> {
+        let new_head = new_heads[index].unwrap();
+        self.wall_collision(&new_head)?;
+        if self.get_obstacle_cells().contains(&new_head) {
+            return Err("Snake hit an obstacle");
+        }
+
+        // An obstacle that just moved from the new head onto the old one
+        // swapped cells with the head on the way.
+        let old_head = self.snakes[index].body.front().unwrap();
+        let before = self.ticks.saturating_sub(1);
+        if self.obstacles.iter().any(|obstacle| {
+            obstacle.cells(before).contains(&new_head)
+                && obstacle.cells(self.ticks).contains(old_head)
+        }) {
+            return Err("Snake hit an obstacle");
+        }
+
+        for (other, other_new_head) in new_heads.iter().enumerate() {
+            let Some(other_new_head) = other_new_head else {
+                continue;
+            };
+            if other == index {
+                continue;
+            }
+            let other_old_head = self.snakes[other].body.front().unwrap();
+            if new_head == *other_old_head && other_new_head == old_head {
+                return Err("Snakes collided head-on");
+            }
+            if new_head == *other_new_head {
+                return Err("Snakes collided head-to-head");
+            }
+        }
+
+        if self.board[new_head.row as usize][new_head.column as usize] {
+            if self.snakes[index].body.contains(&new_head) {
+                if self.snakes[index].has_power_up(PowerUp::Ghost) {
+                    return Ok(());
+                }
+                return Err("Snake hit itself");
+            }
+            return Err("Snake hit another snake");
+        }
         Ok(())
     }
 
     fn move_snake(&mut self) -> Result<(), &'static str> {
-        let old_head = self.snake_body.front().unwrap();
-        match self.current_snake_direction {
-            Direction::Left => self
-                .snake_body
-                .push_front(Position::new(old_head.row, old_head.column - 1)),
-            Direction::Up => self
-                .snake_body
-                .push_front(Position::new(old_head.row - 1, old_head.column)),
-            Direction::Right => self
-                .snake_body
-                .push_front(Position::new(old_head.row, old_head.column + 1)),
-            Direction::Down => self
-                .snake_body
-                .push_front(Position::new(old_head.row + 1, old_head.column)),
-        }
-
-        self.check_if_hit_wall()?;
-        self.check_if_hit_snake()?;
-
-        let new_head = self.snake_body.front().unwrap();
-        self.board[new_head.row as usize][new_head.column as usize] = true;
-
-        if self.snake_found_food() {
-            self.spawn_new_food()?;
+        let new_heads: Vec<Option<Position>> = self
+            .snakes
+            .iter()
+            .map(|snake| {
+                snake
+                    .alive
+                    .then(|| self.next_position(*snake.body.front().unwrap(), snake.direction))
+            })
+            .collect();
+        let outcomes: Vec<Option<Result<(), &'static str>>> = (0..self.snakes.len())
+            .map(|index| new_heads[index].map(|_| self.collision(index, &new_heads)))
+            .collect();
+
+        let mut game_over_cause = None;
+        let mut eaten = Vec::new();
+        for (index, outcome) in outcomes.into_iter().enumerate() {
+            match outcome {
+                Some(Err(_))
+                    if self.snakes[index].has_power_up(PowerUp::Shield)
+                        && !self.in_bounds(new_heads[index].unwrap()) =>
+                {
+                    // The shield takes the hit and the snake waits a tick for
+                    // its owner to turn.
+                    self.snakes[index].end_power_up(PowerUp::Shield);
+                }
+                Some(Err(cause)) => {
+                    // Dead snakes stay on the board as obstacles until the round ends.
+                    self.snakes[index].alive = false;
+                    game_over_cause = Some(cause);
+                    self.events.push(GameEvent::Died {
+                        snake: index,
+                        cause,
+                    });
+                }
+                Some(Ok(())) => {
+                    let new_head = new_heads[index].unwrap();
+                    self.board[new_head.row as usize][new_head.column as usize] = true;
+                    let kind = self.food_at(new_head).map(|item| self.food[item].kind);
+                    let snake = &mut self.snakes[index];
+                    snake.body.push_front(new_head);
+                    if let Some(kind) = kind {
+                        snake.hunger = 0;
+                        snake.score += kind.points();
+                        if let Some(power_up) = kind.power_up() {
+                            snake.grant_power_up(power_up);
+                        }
+                        if kind == FoodKind::Shrink {
+                            snake.pending_growth = 0;
+                        } else {
+                            snake.pending_growth += self.settings.growth_per_food;
+                        }
+                        eaten.push((new_head, kind));
+                        self.events.push(GameEvent::AteFood { snake: index, kind });
+                    }
+                    let too_long = self
+                        .settings
+                        .max_length
+                        .is_some_and(|max_length| snake.body.len() > max_length);
+                    if too_long {
+                        snake.pending_growth = 0;
+                    }
+                    if snake.pending_growth > 0 {
+                        snake.pending_growth -= 1;
+                    } else {
+                        self.pop_tail(index);
+                    }
+                    if kind == Some(FoodKind::Shrink) {
+                        for _ in 0..SHRINK_SEGMENTS.min(self.snakes[index].body.len() - 1) {
+                            self.pop_tail(index);
+                        }
+                    }
+                }
+                None => (),
+            }
+        }
+        if let Some(cause) = self.cut_by_obstacles() {
+            game_over_cause = Some(cause);
+        }
+        self.finish_round(game_over_cause)?;
+
+        for (position, kind) in &eaten {
+            self.food.retain(|food| food.position != *position);
+            match kind {
+                FoodKind::SpeedBoost => self.set_speed(SPEED_BOOST),
+                FoodKind::SlowMotion => self.set_speed(SLOW_MOTION),
+                _ => (),
+            }
+        }
+        if !eaten.is_empty() {
+            self.fill_food();
+            if self.food.is_empty() {
+                // Only a snake that just ate can have filled the board.
+                if let Some(winner) = self.snakes.iter().position(|snake| snake.alive) {
+                    self.events.push(GameEvent::Won { snake: winner });
+                }
+                return Err("No room to spawn food");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ends the round with `cause` if a snake died and too few are left to
+    /// play on: none in single-player games, one in multiplayer games, who
+    /// wins.
+    fn finish_round(&mut self, cause: Option<&'static str>) -> Result<(), &'static str> {
+        let Some(cause) = cause else {
+            return Ok(());
+        };
+        let alive = self.snakes.iter().filter(|snake| snake.alive).count();
+        let round_over = if self.snakes.len() > 1 {
+            alive <= 1
         } else {
-            let tail = self.snake_body.pop_back().unwrap();
-            self.board[tail.row as usize][tail.column as usize] = false;
+            alive == 0
+        };
+        if !round_over {
+            return Ok(());
+        }
+        if let Some(winner) = self.snakes.iter().position(|snake| snake.alive) {
+            self.events.push(GameEvent::Won { snake: winner });
         }
+        Err(cause)
+    }
 
+    /// Applies the rules of the mode in the settings: survival points and
+    /// hunger, then the time limit. When time runs out in a multiplayer game
+    /// the highest score wins, unless it is shared.
+    fn apply_mode(&mut self) -> Result<(), &'static str> {
+        let mode = self.settings.mode;
+        let points = mode.tick_points(self.ticks);
+        let mut cause = None;
+        for index in 0..self.snakes.len() {
+            let snake = &mut self.snakes[index];
+            if !snake.alive {
+                continue;
+            }
+            snake.score += points;
+            let Some(hunger_ticks) = mode.hunger_ticks() else {
+                continue;
+            };
+            snake.hunger += 1;
+            if snake.hunger < hunger_ticks {
+                continue;
+            }
+            snake.hunger = 0;
+            if snake.body.len() > 1 {
+                self.pop_tail(index);
+            } else {
+                snake.alive = false;
+                cause = Some("Snake starved");
+                self.events.push(GameEvent::Died {
+                    snake: index,
+                    cause: "Snake starved",
+                });
+            }
+        }
+        self.finish_round(cause)?;
+
+        if self.get_time_left_ms() == Some(0) {
+            if self.snakes.len() > 1 {
+                let best = self.snakes.iter().map(|snake| snake.score).max();
+                let leaders: Vec<usize> = (0..self.snakes.len())
+                    .filter(|index| Some(self.snakes[*index].score) == best)
+                    .collect();
+                if let [winner] = leaders[..] {
+                    self.events.push(GameEvent::Won { snake: winner });
+                }
+            }
+            return Err("Time is up");
+        }
         Ok(())
     }
 
+    /// Obstacles that moved onto a snake cut off the body from that segment to
+    /// the tail, or kill the snake if they landed on its head. Returns the
+    /// cause if a snake died.
+    fn cut_by_obstacles(&mut self) -> Option<&'static str> {
+        let cells = self.get_obstacle_cells();
+        let mut cause = None;
+        for index in 0..self.snakes.len() {
+            if !self.snakes[index].alive {
+                continue;
+            }
+            let hit = self.snakes[index]
+                .body
+                .iter()
+                .position(|segment| cells.contains(segment));
+            match hit {
+                Some(0) => {
+                    self.snakes[index].alive = false;
+                    cause = Some("Snake hit an obstacle");
+                    self.events.push(GameEvent::Died {
+                        snake: index,
+                        cause: "Snake hit an obstacle",
+                    });
+                }
+                Some(segment) => {
+                    while self.snakes[index].body.len() > segment {
+                        self.pop_tail(index);
+                    }
+                }
+                None => (),
+            }
+        }
+        cause
+    }
+
+    /// Removes the last segment of the snake at `index`. A ghost's body can
+    /// cross itself, so the cell is only freed once no segment covers it.
+    fn pop_tail(&mut self, index: usize) {
+        let snake = &mut self.snakes[index];
+        let tail = snake.body.pop_back().unwrap();
+        if !snake.body.contains(&tail) {
+            self.board[tail.row as usize][tail.column as usize] = false;
+        }
+    }
+
+    /// Moves food near a magnet's head one step closer to it, as long as the
+    /// cell on the way is free. Distances and steps follow the grid.
+    fn pull_food(&mut self) {
+        let topology = self.settings.grid.topology();
+        let reach = self.obstacle_reach();
+        for index in 0..self.snakes.len() {
+            let snake = &self.snakes[index];
+            if !snake.alive || !snake.has_power_up(PowerUp::Magnet) {
+                continue;
+            }
+            let head = *snake.body.front().unwrap();
+            for item in 0..self.food.len() {
+                let position = self.food[item].position;
+                let distance = topology.distance(position, head);
+                if distance <= 1 || distance > MAGNET_RADIUS {
+                    continue;
+                }
+                let Some(step) = topology
+                    .directions()
+                    .iter()
+                    .map(|direction| topology.step(position, *direction))
+                    .min_by_key(|step| topology.distance(*step, head))
+                else {
+                    continue;
+                };
+                if self.is_free(step) && self.food_at(step).is_none() && !reach.contains(&step) {
+                    self.food[item].position = step;
+                }
+            }
+        }
+    }
+
+    /// Summary of the game for HUDs.
+    pub fn view(&self) -> GameView {
+        GameView {
+            snakes: self
+                .snakes
+                .iter()
+                .map(|snake| SnakeView {
+                    score: snake.score,
+                    alive: snake.alive,
+                    length: snake.body.len(),
+                    power_ups: snake.power_ups.clone(),
+                })
+                .collect(),
+            speed: self.speed,
+        }
+    }
+
+    fn set_speed(&mut self, speed: f64) {
+        self.speed = speed;
+        self.speed_ticks_left = SPEED_EFFECT_TICKS;
+    }
+
+    /// Counts down timed effects and replaces food that has expired.
+    fn age_items(&mut self) {
+        for snake in &mut self.snakes {
+            for active in &mut snake.power_ups {
+                active.ticks_left = active.ticks_left.saturating_sub(1);
+            }
+            snake.power_ups.retain(|active| active.ticks_left > 0);
+        }
+        if self.speed_ticks_left > 0 {
+            self.speed_ticks_left -= 1;
+            if self.speed_ticks_left == 0 {
+                self.speed = 1.0;
+            }
+        }
+        for food in &mut self.food {
+            if let Some(ticks_left) = &mut food.ticks_left {
+                *ticks_left = ticks_left.saturating_sub(1);
+            }
+        }
+        let count = self.food.len();
+        self.food.retain(|food| food.ticks_left != Some(0));
+        if self.food.len() < count {
+            self.fill_food();
+        }
+    }
+
     pub fn update_game(&mut self) -> Result<(), &'static str> {
+        self.events.clear();
+        // Obstacles move first, so a snake can't slip through one that is
+        // moving into the cell it is entering or swapping cells with its head.
+        self.ticks += 1;
+        self.elapsed_ms += (TICK_MILLIS as f64 / self.speed) as u64;
         self.update_direction();
         self.move_snake()?;
+        self.age_items();
+        self.pull_food();
 
-        Ok(())
+        self.apply_mode()
     }
 }
 
@@ -336,7 +1420,10 @@ mod test {
             current_snake_direction,
             food_position,
         );
-        assert_eq!(bad_game.snake_body.front().unwrap(), &Position::new(1, 1));
+        assert_eq!(
+            bad_game.snakes[0].body.front().unwrap(),
+            &Position::new(1, 1)
+        );
     }
 
     #[test]
@@ -501,108 +1588,131 @@ mod test {
     }
 
     #[test]
-    fn handle_key_next_direction_default() {
+    fn input_queue_starts_empty() {
         let game = create_basic_game();
-        assert_eq!(game.next_snake_position, None);
+        assert!(game.snakes[0].input_queue.is_empty());
     }
 
     #[test]
     fn update_direction_not_opposite_movement() {
         let mut game = create_basic_game();
 
-        game.handle_key(Key::Down);
-        assert_eq!(game.next_snake_position, Some(Direction::Down));
+        game.set_next_direction(Direction::Down);
+        assert_eq!(game.snakes[0].input_queue.front(), Some(&Direction::Down));
         game.update_direction();
-        assert_eq!(game.current_snake_direction, Direction::Down);
-        assert_eq!(game.next_snake_position, None);
+        assert_eq!(game.snakes[0].direction, Direction::Down);
+        assert!(game.snakes[0].input_queue.is_empty());
 
-        game.handle_key(Key::Left);
-        assert_eq!(game.next_snake_position, Some(Direction::Left));
+        game.set_next_direction(Direction::Left);
+        assert_eq!(game.snakes[0].input_queue.front(), Some(&Direction::Left));
         game.update_direction();
-        assert_eq!(game.current_snake_direction, Direction::Left);
+        assert_eq!(game.snakes[0].direction, Direction::Left);
 
-        game.handle_key(Key::Up);
-        assert_eq!(game.next_snake_position, Some(Direction::Up));
+        game.set_next_direction(Direction::Up);
+        assert_eq!(game.snakes[0].input_queue.front(), Some(&Direction::Up));
         game.update_direction();
-        assert_eq!(game.current_snake_direction, Direction::Up);
+        assert_eq!(game.snakes[0].direction, Direction::Up);
 
-        game.handle_key(Key::Right);
-        assert_eq!(game.next_snake_position, Some(Direction::Right));
+        game.set_next_direction(Direction::Right);
+        assert_eq!(game.snakes[0].input_queue.front(), Some(&Direction::Right));
         game.update_direction();
-        assert_eq!(game.current_snake_direction, Direction::Right);
+        assert_eq!(game.snakes[0].direction, Direction::Right);
+    }
+
+    #[test]
+    fn direction_opposite() {
+        assert_eq!(Direction::Left.opposite(), Direction::Right);
+        assert_eq!(Direction::UpRight.opposite(), Direction::DownLeft);
     }
 
     #[test]
     fn update_direction_opposite_movement() {
         let mut game = create_basic_game();
 
-        game.handle_key(Key::Left);
+        game.set_next_direction(Direction::Left);
         game.update_direction();
-        assert_eq!(game.current_snake_direction, Direction::Right);
+        assert_eq!(game.snakes[0].direction, Direction::Right);
 
-        game.handle_key(Key::Up);
+        game.set_next_direction(Direction::Up);
         game.update_direction();
-        game.handle_key(Key::Down);
+        game.set_next_direction(Direction::Down);
         game.update_direction();
-        assert_eq!(game.current_snake_direction, Direction::Up);
+        assert_eq!(game.snakes[0].direction, Direction::Up);
 
-        game.handle_key(Key::Right);
+        game.set_next_direction(Direction::Right);
         game.update_direction();
-        game.handle_key(Key::Left);
+        game.set_next_direction(Direction::Left);
         game.update_direction();
-        assert_eq!(game.current_snake_direction, Direction::Right);
+        assert_eq!(game.snakes[0].direction, Direction::Right);
 
-        game.handle_key(Key::Down);
+        game.set_next_direction(Direction::Down);
         game.update_direction();
-        game.handle_key(Key::Up);
+        game.set_next_direction(Direction::Up);
         game.update_direction();
-        assert_eq!(game.current_snake_direction, Direction::Down);
+        assert_eq!(game.snakes[0].direction, Direction::Down);
 
-        game.handle_key(Key::Left);
+        game.set_next_direction(Direction::Left);
         game.update_direction();
-        game.handle_key(Key::Right);
+        game.set_next_direction(Direction::Right);
         game.update_direction();
-        assert_eq!(game.current_snake_direction, Direction::Left);
+        assert_eq!(game.snakes[0].direction, Direction::Left);
     }
 
     #[test]
     fn snake_does_not_hit_wall() {
-        let game = create_basic_game();
-        let res = game.check_if_hit_wall();
-        assert!(res.is_ok());
+        let mut game = create_basic_game();
+        assert!(game.update_game().is_ok());
+        assert!(game.snakes[0].alive);
     }
 
     #[test]
     fn snake_hits_wall() {
         let mut game = create_basic_game();
-        game.snake_body.pop_front().unwrap();
-        game.snake_body.push_front(Position::new(10, 10));
-        let res = game.check_if_hit_wall();
-        assert!(res.is_err());
+        for _ in 0..5 {
+            assert!(game.update_game().is_ok());
+        }
+        assert_eq!(game.update_game(), Err("Snake head hit left or right wall"));
+        assert!(!game.snakes[0].alive);
     }
 
     #[test]
     fn snake_does_not_hit_snake() {
-        let mut game = create_basic_game();
-        game.snake_body.pop_front().unwrap();
-        game.snake_body.push_front(Position::new(5, 5));
-        let res = game.check_if_hit_snake();
-        assert!(res.is_ok());
+        let mut game = create_game_with_food_ahead(5, FoodKind::Normal);
+        game.set_food(vec![Food::new(Position::new(0, 0), FoodKind::Normal)]);
+        // Turning back alongside its own body is fine.
+        for direction in [Direction::Down, Direction::Left, Direction::Left] {
+            game.set_next_direction(direction);
+            assert!(game.update_game().is_ok());
+        }
+        assert!(game.snakes[0].alive);
+    }
+
+    #[test]
+    fn snake_hits_snake() {
+        let mut game = create_game_with_food_ahead(5, FoodKind::Normal);
+        game.set_food(vec![Food::new(Position::new(0, 0), FoodKind::Normal)]);
+        for direction in [Direction::Down, Direction::Left] {
+            game.set_next_direction(direction);
+            assert!(game.update_game().is_ok());
+        }
+        game.set_next_direction(Direction::Up);
+        assert_eq!(game.update_game(), Err("Snake hit itself"));
+        assert!(!game.snakes[0].alive);
     }
 
     #[test]
     fn snake_does_not_find_food() {
         let game = create_basic_game();
         let res = game.snake_found_food();
-        assert!(!res);
+        assert_eq!(res, None);
     }
     #[test]
     fn snake_does_find_food() {
         let mut game = create_basic_game();
-        game.snake_body.pop_front().unwrap();
-        game.snake_body.push_front(Position::new(2, 2));
+        game.snakes[0].body.pop_front().unwrap();
+        game.snakes[0].body.push_front(Position::new(2, 2));
         let res = game.snake_found_food();
-        assert!(res);
+        assert_eq!(res, Some(0));
     }
     #[test]
     fn spawn_food_fails_with_full_small_board() {
@@ -622,16 +1732,584 @@ mod test {
     #[test]
     fn spawn_food_works() {
         let mut game = create_basic_game();
-        game.handle_key(Key::Up);
+        game.set_next_direction(Direction::Up);
         game.update_direction();
 
         assert!(game.move_snake().is_ok());
         assert!(game.move_snake().is_ok());
 
-        game.handle_key(Key::Left);
+        game.set_next_direction(Direction::Left);
         game.update_direction();
 
         assert!(game.move_snake().is_ok());
         assert!(game.move_snake().is_ok());
     }
+
+    fn create_two_player_game(
+        first: &[(i32, i32)],
+        first_direction: Direction,
+        second: &[(i32, i32)],
+        second_direction: Direction,
+    ) -> Game {
+        let body = |cells: &[(i32, i32)]| {
+            cells
+                .iter()
+                .map(|(row, column)| Position::new(*row, *column))
+                .collect()
+        };
+        let snakes = vec![
+            Snake::new(body(first), first_direction),
+            Snake::new(body(second), second_direction),
+        ];
+        Game::new_multiplayer(5, 5, snakes, Position::new(4, 4))
+    }
+
+    #[test]
+    fn new_multiplayer_overlapping_snakes() {
+        let default_game = Game::default();
+        let game = create_two_player_game(
+            &[(0, 1), (0, 0)],
+            Direction::Right,
+            &[(0, 1), (1, 1)],
+            Direction::Up,
+        );
+        assert_eq!(game, default_game);
+    }
+
+    #[test]
+    fn input_queue_applies_one_turn_per_tick() {
+        let mut game = create_basic_game();
+        game.set_next_direction(Direction::Up);
+        game.set_next_direction(Direction::Left);
+        game.update_direction();
+        assert_eq!(game.snakes[0].direction, Direction::Up);
+        game.update_direction();
+        assert_eq!(game.snakes[0].direction, Direction::Left);
+    }
+
+    #[test]
+    fn input_queue_is_bounded() {
+        let mut game = create_basic_game();
+        for _ in 0..MAX_QUEUED_INPUTS + 2 {
+            game.set_next_direction(Direction::Up);
+        }
+        assert_eq!(game.snakes[0].input_queue.len(), MAX_QUEUED_INPUTS);
+    }
+
+    #[test]
+    fn queue_direction_steers_each_snake() {
+        let mut game = create_two_player_game(
+            &[(0, 1), (0, 0)],
+            Direction::Right,
+            &[(4, 1), (4, 2)],
+            Direction::Left,
+        );
+        game.queue_direction(1, Direction::Up);
+        game.queue_direction(0, Direction::Down);
+        game.queue_direction(2, Direction::Down);
+        game.update_direction();
+        assert_eq!(game.snakes[0].direction, Direction::Down);
+        assert_eq!(game.snakes[1].direction, Direction::Up);
+    }
+
+    #[test]
+    fn head_to_head_kills_both_snakes() {
+        let mut game = create_two_player_game(
+            &[(2, 1), (2, 0)],
+            Direction::Right,
+            &[(2, 3), (2, 4)],
+            Direction::Left,
+        );
+        let res = game.update_game();
+        assert_eq!(res.unwrap_err(), "Snakes collided head-to-head");
+        assert!(!game.snakes[0].is_alive());
+        assert!(!game.snakes[1].is_alive());
+    }
+
+    #[test]
+    fn head_on_swap_kills_both_snakes() {
+        let mut game = create_two_player_game(
+            &[(2, 1), (2, 0)],
+            Direction::Right,
+            &[(2, 2), (2, 3)],
+            Direction::Left,
+        );
+        let res = game.update_game();
+        assert_eq!(res.unwrap_err(), "Snakes collided head-on");
+        assert!(!game.snakes[0].is_alive());
+        assert!(!game.snakes[1].is_alive());
+    }
+
+    #[test]
+    fn head_to_body_kills_only_attacker() {
+        let mut game = create_two_player_game(
+            &[(1, 2), (1, 1)],
+            Direction::Down,
+            &[(2, 3), (2, 2), (2, 1)],
+            Direction::Right,
+        );
+        let res = game.update_game();
+        assert_eq!(res.unwrap_err(), "Snake hit another snake");
+        assert!(!game.snakes[0].is_alive());
+        assert!(game.snakes[1].is_alive());
+    }
+
+    #[test]
+    fn head_into_moving_tail_is_a_collision() {
+        let mut game = create_two_player_game(
+            &[(1, 0), (0, 0)],
+            Direction::Down,
+            &[(3, 1), (3, 0), (2, 0)],
+            Direction::Right,
+        );
+        let res = game.update_game();
+        assert_eq!(res.unwrap_err(), "Snake hit another snake");
+        assert!(game.snakes[1].is_alive());
+    }
+
+    #[test]
+    fn score_is_tracked_per_snake() {
+        let mut game = create_two_player_game(
+            &[(4, 3), (4, 2)],
+            Direction::Right,
+            &[(0, 1), (0, 0)],
+            Direction::Right,
+        );
+        assert!(game.update_game().is_ok());
+        assert_eq!(game.snakes[0].get_score(), 1);
+        assert_eq!(game.snakes[1].get_score(), 0);
+        assert_eq!(game.get_score(), 1);
+    }
+
+    #[test]
+    fn events_report_turns_and_food_for_last_tick_only() {
+        let mut game = create_two_player_game(
+            &[(3, 4), (2, 4)],
+            Direction::Down,
+            &[(0, 1), (0, 0)],
+            Direction::Right,
+        );
+        game.queue_direction(1, Direction::Down);
+        assert!(game.update_game().is_ok());
+        assert_eq!(
+            game.get_events(),
+            &[
+                GameEvent::Turned {
+                    snake: 1,
+                    direction: Direction::Down
+                },
+                GameEvent::AteFood {
+                    snake: 0,
+                    kind: FoodKind::Normal
+                },
+            ]
+        );
+        game.queue_direction(0, Direction::Left);
+        assert!(game.update_game().is_ok());
+        assert_eq!(
+            game.get_events(),
+            &[GameEvent::Turned {
+                snake: 0,
+                direction: Direction::Left
+            }]
+        );
+    }
+
+    #[test]
+    fn events_report_last_snake_standing_as_winner() {
+        let mut game = create_two_player_game(
+            &[(1, 2), (1, 1)],
+            Direction::Down,
+            &[(2, 3), (2, 2), (2, 1)],
+            Direction::Right,
+        );
+        assert!(game.update_game().is_err());
+        assert_eq!(
+            game.get_events(),
+            &[
+                GameEvent::Died {
+                    snake: 0,
+                    cause: "Snake hit another snake"
+                },
+                GameEvent::Won { snake: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn events_report_filling_the_board_as_a_win() {
+        let mut game = create_almost_full_game(4);
+        assert_eq!(game.update_game().unwrap_err(), "No room to spawn food");
+        assert_eq!(
+            game.get_events(),
+            &[
+                GameEvent::AteFood {
+                    snake: 0,
+                    kind: FoodKind::Normal
+                },
+                GameEvent::Won { snake: 0 }
+            ]
+        );
+    }
+
+    fn create_game_with_food_ahead(length: i32, kind: FoodKind) -> Game {
+        let mut snake_body = VecDeque::new();
+        for column in 0..length {
+            snake_body.push_front(Position::new(4, column));
+        }
+        let mut game = Game::new(10, 10, snake_body, Direction::Right, Position::new(0, 0));
+        game.set_food(vec![Food::new(Position::new(4, length), kind)]);
+        game
+    }
+
+    #[test]
+    fn golden_food_scores_bonus_and_expires() {
+        let mut game = create_game_with_food_ahead(1, FoodKind::Golden);
+        assert!(game.update_game().is_ok());
+        assert_eq!(game.get_score(), GOLDEN_POINTS);
+        assert_eq!(game.get_snake_positions().len(), 2);
+
+        let golden = Food::new(Position::new(0, 9), FoodKind::Golden);
+        game.set_food(vec![golden]);
+        for _ in 0..FoodKind::Golden.lifetime().unwrap() - 1 {
+            game.age_items();
+        }
+        assert_eq!(game.get_food()[0].get_ticks_left(), Some(1));
+        game.age_items();
+        assert_eq!(game.get_food().len(), 1);
+        assert_ne!(game.get_food()[0], golden);
+    }
+
+    #[test]
+    fn shrink_food_shortens_snake() {
+        let mut game = create_game_with_food_ahead(4, FoodKind::Shrink);
+        assert!(game.update_game().is_ok());
+        assert_eq!(game.get_score(), 1);
+        assert_eq!(game.get_snake_positions().len(), 2);
+        let covered = game.board.iter().flatten().filter(|cell| **cell).count();
+        assert_eq!(covered, 2);
+    }
+
+    #[test]
+    fn speed_items_wear_off() {
+        let mut game = create_game_with_food_ahead(1, FoodKind::SpeedBoost);
+        assert!(game.update_game().is_ok());
+        assert_eq!(game.get_speed(), SPEED_BOOST);
+        for _ in 1..SPEED_EFFECT_TICKS {
+            game.age_items();
+        }
+        assert_eq!(game.get_speed(), 1.0);
+    }
+
+    #[test]
+    fn eaten_food_is_replenished_up_to_max_food() {
+        let mut game = create_game_with_food_ahead(1, FoodKind::Normal);
+        game.set_settings(GameSettings {
+            max_food: 3,
+            special_food: false,
+            ..GameSettings::default()
+        });
+        assert_eq!(game.get_food().len(), 3);
+        let ahead = Position::new(4, 1);
+        assert_eq!(game.food_at(ahead), Some(0));
+
+        assert!(game.update_game().is_ok());
+        assert_eq!(game.snake_found_food(), None);
+        assert_eq!(game.get_score(), 1);
+        assert_eq!(game.get_food().len(), 3);
+        let positions: Vec<Position> = game.get_food().iter().map(|food| food.position).collect();
+        assert!(!positions.contains(&ahead));
+        assert!(positions[1..]
+            .iter()
+            .all(|position| *position != positions[0]));
+    }
+
+    #[test]
+    fn spawn_food_keeps_old_item_when_board_is_full() {
+        let mut game = create_almost_full_game(2);
+        game.board[0][1] = true;
+        assert!(game.spawn_new_food().is_err());
+        assert_eq!(game.get_food_position(), Some(&Position::new(0, 1)));
+    }
+
+    #[test]
+    fn ghost_passes_through_own_body() {
+        let mut game = create_game_with_food_ahead(5, FoodKind::Normal);
+        game.set_food(vec![Food::new(Position::new(0, 0), FoodKind::Normal)]);
+        game.snakes[0].grant_power_up(PowerUp::Ghost);
+        for direction in [Direction::Down, Direction::Left, Direction::Up] {
+            game.set_next_direction(direction);
+            assert!(game.update_game().is_ok());
+        }
+        // The head is back on the last body segment and the snake lives on.
+        assert_eq!(game.snakes[0].body.front(), Some(&Position::new(4, 3)));
+        assert_eq!(game.snakes[0].body.back(), Some(&Position::new(4, 3)));
+        assert_eq!(game.snakes[0].body.len(), 5);
+        assert!(game.snakes[0].alive);
+        assert!(!game
+            .get_events()
+            .iter()
+            .any(|event| matches!(event, GameEvent::Died { .. })));
+
+        // The tail leaving a cell the head still covers keeps it occupied.
+        assert!(game.update_game().is_ok());
+        assert!(game.board[4][3]);
+    }
+
+    #[test]
+    fn shield_absorbs_one_wall_hit() {
+        let mut game = create_basic_game();
+        for _ in 0..5 {
+            assert!(game.update_game().is_ok());
+        }
+        game.snakes[0].grant_power_up(PowerUp::Shield);
+        assert!(game.update_game().is_ok());
+        assert_eq!(game.snakes[0].body.front(), Some(&Position::new(4, 9)));
+        assert!(!game.snakes[0].has_power_up(PowerUp::Shield));
+        assert!(game.update_game().is_err());
+    }
+
+    #[test]
+    fn magnet_pulls_nearby_food() {
+        let mut game = create_basic_game();
+        game.snakes[0].grant_power_up(PowerUp::Magnet);
+        game.set_food(vec![
+            Food::new(Position::new(1, 4), FoodKind::Normal),
+            Food::new(Position::new(9, 9), FoodKind::Golden),
+        ]);
+        game.pull_food();
+        assert_eq!(game.get_food()[0].position, Position::new(2, 4));
+        assert_eq!(game.get_food()[1].position, Position::new(9, 9));
+    }
+
+    #[test]
+    fn view_reports_power_ups_counting_down() {
+        let mut game = create_game_with_food_ahead(1, FoodKind::Ghost);
+        assert!(game.update_game().is_ok());
+        let view = game.view();
+        assert_eq!(view.snakes[0].length, 2);
+        assert_eq!(
+            view.snakes[0].power_ups,
+            vec![ActivePowerUp {
+                power_up: PowerUp::Ghost,
+                ticks_left: PowerUp::Ghost.duration() - 1
+            }]
+        );
+    }
+
+    #[test]
+    fn with_length_lays_body_out_behind_head() {
+        let snake = Snake::with_length(Position::new(2, 2), Direction::Up, 3);
+        assert_eq!(
+            snake.body,
+            VecDeque::from([
+                Position::new(2, 2),
+                Position::new(3, 2),
+                Position::new(4, 2)
+            ])
+        );
+        assert_eq!(
+            Snake::with_length(Position::new(0, 0), Direction::Left, 0)
+                .body
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn growth_is_spread_over_ticks_and_capped() {
+        let mut game = create_game_with_food_ahead(2, FoodKind::Normal);
+        game.set_settings(GameSettings {
+            special_food: false,
+            growth_per_food: 3,
+            max_length: Some(4),
+            ..GameSettings::default()
+        });
+        let mut lengths = Vec::new();
+        for _ in 0..4 {
+            assert!(game.update_game().is_ok());
+            // Keep the respawned food out of the snake's way.
+            game.set_food(vec![Food::new(Position::new(0, 0), FoodKind::Normal)]);
+            lengths.push(game.get_snake_positions().len());
+        }
+        assert_eq!(lengths, vec![3, 4, 4, 4]);
+    }
+
+    #[test]
+    fn snake_dies_on_obstacle() {
+        let mut game = create_basic_game();
+        game.set_obstacles(vec![Obstacle::Block {
+            position: Position::new(4, 6),
+        }]);
+        assert!(game.update_game().is_ok());
+        assert_eq!(game.update_game(), Err("Snake hit an obstacle"));
+        assert!(!game.is_free(Position::new(4, 6)));
+    }
+
+    #[test]
+    fn snake_dies_swapping_cells_with_a_patrol() {
+        let mut game = create_basic_game();
+        game.set_obstacles(vec![Obstacle::Patrol {
+            waypoints: vec![Position::new(4, 7), Position::new(4, 0)],
+            period: 1,
+        }]);
+        // The patrol reaches the cell just ahead of the head, then the two
+        // would swap cells on the next tick.
+        assert!(game.update_game().is_ok());
+        assert_eq!(game.get_obstacle_cells(), vec![Position::new(4, 6)]);
+        assert_eq!(game.update_game(), Err("Snake hit an obstacle"));
+        assert!(!game.snakes[0].alive);
+    }
+
+    #[test]
+    fn patrol_cuts_body_and_keeps_food_clear() {
+        let snake = Snake::with_length(Position::new(4, 5), Direction::Right, 6);
+        let mut game = Game::new_multiplayer(10, 10, vec![snake], Position::new(2, 2));
+        game.set_settings(GameSettings {
+            max_food: 20,
+            ..GameSettings::default()
+        });
+        game.set_obstacles(vec![Obstacle::Patrol {
+            waypoints: vec![Position::new(2, 2), Position::new(6, 2)],
+            period: 1,
+        }]);
+        assert!(game
+            .get_food()
+            .iter()
+            .all(|food| food.position.column != 2 || !(2..=6).contains(&food.position.row)));
+        assert_eq!(game.get_food().len(), 20);
+
+        assert!(game.update_game().is_ok());
+        assert_eq!(game.get_obstacle_cells(), vec![Position::new(3, 2)]);
+        assert!(game.update_game().is_ok());
+        assert_eq!(game.get_snake_positions().len(), 5);
+        assert!(game.is_free(Position::new(4, 1)));
+        assert!(game.snakes[0].alive);
+    }
+
+    #[test]
+    fn head_comes_out_of_paired_portal() {
+        let mut game = create_basic_game();
+        game.set_portals(vec![Portal {
+            a: Position::new(4, 6),
+            b: Position::new(1, 1),
+        }]);
+        assert!(game.update_game().is_ok());
+        assert!(game.update_game().is_ok());
+        assert_eq!(
+            game.get_snake_positions().front(),
+            Some(&Position::new(1, 2))
+        );
+        assert!(!game.is_free(Position::new(4, 6)));
+        assert_eq!(
+            game.next_position(Position::new(2, 1), Direction::Up),
+            Position::new(3, 6)
+        );
+    }
+
+    #[test]
+    fn set_snakes_accepts_bodies_split_by_portals() {
+        let mut game = create_basic_game();
+        let body = VecDeque::from([Position::new(1, 2), Position::new(4, 5)]);
+        game.set_snakes(vec![Snake::new(body, Direction::Right)]);
+        assert_eq!(game.get_snake_positions().len(), 2);
+        assert!(!game.is_free(Position::new(4, 5)));
+        assert!(game.is_free(Position::new(4, 4)));
+        assert!(game.is_consistent());
+    }
+
+    #[test]
+    fn time_attack_ends_when_time_is_up() {
+        let mut game = create_basic_game();
+        game.set_settings(GameSettings {
+            mode: GameMode::TimeAttack { seconds: 1 },
+            ..GameSettings::default()
+        });
+        game.queue_direction(0, Direction::Up);
+        for _ in 0..3 {
+            assert!(game.update_game().is_ok());
+        }
+        assert_eq!(game.get_time_left_ms(), Some(250));
+        assert_eq!(game.update_game(), Err("Time is up"));
+    }
+
+    #[test]
+    fn survival_snake_shrinks_then_starves() {
+        let snake = Snake::with_length(Position::new(5, 5), Direction::Down, 2);
+        let mut game = Game::new_multiplayer(60, 60, vec![snake], Position::new(0, 0));
+        game.set_settings(GameSettings {
+            mode: GameMode::Survival,
+            ..GameSettings::default()
+        });
+        let hunger = crate::mode::HUNGER_TICKS as usize;
+        for _ in 0..hunger {
+            assert!(game.update_game().is_ok());
+        }
+        assert_eq!(game.get_snake_positions().len(), 1);
+        assert_eq!(game.get_score(), 1);
+        for _ in 1..hunger {
+            assert!(game.update_game().is_ok());
+        }
+        assert_eq!(game.update_game(), Err("Snake starved"));
+    }
+
+    fn create_game_on(grid: Grid) -> Game {
+        let snake = Snake::with_length(Position::new(4, 4), Direction::Right, 3);
+        let mut game = Game::new_multiplayer(10, 10, vec![snake], Position::new(0, 0));
+        game.set_settings(GameSettings {
+            grid,
+            ..GameSettings::default()
+        });
+        game
+    }
+
+    #[test]
+    fn magnet_pulls_food_along_the_grid() {
+        let mut game = create_game_on(Grid::Octile);
+        game.snakes[0].grant_power_up(PowerUp::Magnet);
+        game.set_food(vec![Food::new(Position::new(1, 1), FoodKind::Normal)]);
+        game.pull_food();
+        assert_eq!(game.get_food()[0].position, Position::new(2, 2));
+
+        let mut game = create_game_on(Grid::Hex);
+        game.snakes[0].grant_power_up(PowerUp::Magnet);
+        game.set_food(vec![Food::new(Position::new(2, 6), FoodKind::Normal)]);
+        game.pull_food();
+        assert_eq!(game.get_food()[0].position, Position::new(3, 5));
+    }
+
+    #[test]
+    fn hex_snake_goes_up_along_diagonals() {
+        let mut game = create_game_on(Grid::Hex);
+        game.queue_direction(0, Direction::Up);
+        assert!(game.update_game().is_ok());
+        assert_eq!(game.get_current_direction(), Direction::UpRight);
+        assert_eq!(game.get_snake_positions()[0], Position::new(3, 4));
+        assert!(game.update_game().is_ok());
+        assert_eq!(game.get_snake_positions()[0], Position::new(2, 5));
+        game.queue_direction(0, Direction::UpLeft);
+        assert!(game.update_game().is_ok());
+        assert_eq!(game.get_current_direction(), Direction::UpLeft);
+        assert_eq!(game.get_snake_positions()[0], Position::new(1, 4));
+    }
+
+    #[test]
+    fn octile_keys_turn_by_half_a_right_angle() {
+        let mut game = create_game_on(Grid::Octile);
+        game.queue_direction(0, Direction::Up);
+        game.queue_direction(0, Direction::Up);
+        assert!(game.update_game().is_ok());
+        assert_eq!(game.get_snake_positions()[0], Position::new(3, 5));
+        assert!(game.update_game().is_ok());
+        assert_eq!(game.get_current_direction(), Direction::Up);
+        assert_eq!(game.get_snake_positions()[0], Position::new(2, 5));
+    }
+
+    #[test]
+    fn square_grid_ignores_diagonals() {
+        let mut game = create_game_on(Grid::Square);
+        game.queue_direction(0, Direction::UpLeft);
+        assert!(game.update_game().is_ok());
+        assert_eq!(game.get_current_direction(), Direction::Right);
+    }
 }
@@ -0,0 +1,283 @@
+use std::collections::HashMap;
+
+use rust_snake::game::Direction;
+
+use crate::keymap::Action;
+
+/// How far the stick has to be pushed before it counts, as a fraction of its
+/// range. Worn sticks rarely rest exactly at 0.
+pub static DEADZONE: f64 = 0.4;
+
+/// Direction the stick at `(x, y)` points in, or `None` inside the deadzone.
+/// Diagonals go to whichever axis is pushed further; y grows downwards.
+pub fn stick_direction(x: f64, y: f64, deadzone: f64) -> Option<Direction> {
+    if x.abs().max(y.abs()) < deadzone {
+        None
+    } else if x.abs() > y.abs() {
+        Some(if x > 0.0 {
+            Direction::Right
+        } else {
+            Direction::Left
+        })
+    } else {
+        Some(if y > 0.0 {
+            Direction::Down
+        } else {
+            Direction::Up
+        })
+    }
+}
+
+/// What a controller did, in terms the game cares about. Pads are told apart
+/// by the id the backend gives them. Only the gilrs backend produces these.
+#[derive(PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(not(feature = "gamepad"), allow(dead_code))]
+enum PadEvent {
+    Connected,
+    Disconnected,
+    StickX(f64),
+    /// Grows downwards, like rows.
+    StickY(f64),
+    DPad(Direction),
+    Start,
+}
+
+#[derive(Default, Clone, Copy)]
+struct Stick {
+    x: f64,
+    y: f64,
+    direction: Option<Direction>,
+}
+
+/// Turns controller input into the same actions the keymap produces. Pads
+/// steer players in the order they were connected; a pad that disconnects
+/// frees its player for the next one to connect, so a reconnected pad gets
+/// its snake back even though the backend gives it a new id.
+#[derive(Default)]
+pub struct Gamepads {
+    source: Option<backend::Source>,
+    /// Id of the pad steering each player, if any.
+    players: Vec<Option<usize>>,
+    sticks: HashMap<usize, Stick>,
+}
+
+impl Gamepads {
+    pub fn new() -> Gamepads {
+        let mut gamepads = Gamepads::default();
+        match backend::Source::open() {
+            Ok(source) => {
+                for pad in source.connected() {
+                    gamepads.player(pad);
+                }
+                gamepads.source = Some(source);
+            }
+            Err(err) => {
+                if backend::ENABLED {
+                    eprintln!("Gamepads are unavailable.");
+                    eprintln!("{err}");
+                }
+            }
+        }
+        gamepads
+    }
+
+    /// The next action from any pad, or `None` once nothing is left to read.
+    /// Meant to be called every frame.
+    pub fn action(&mut self) -> Option<Action> {
+        while let Some((pad, event)) = self.source.as_mut()?.next_event() {
+            if let Some(action) = self.handle(pad, event) {
+                return Some(action);
+            }
+        }
+        None
+    }
+
+    /// The player `pad` steers, handing it the first free one if it has none.
+    fn player(&mut self, pad: usize) -> usize {
+        if let Some(player) = self.players.iter().position(|slot| *slot == Some(pad)) {
+            return player;
+        }
+        match self.players.iter().position(Option::is_none) {
+            Some(player) => {
+                self.players[player] = Some(pad);
+                player
+            }
+            None => {
+                self.players.push(Some(pad));
+                self.players.len() - 1
+            }
+        }
+    }
+
+    fn handle(&mut self, pad: usize, event: PadEvent) -> Option<Action> {
+        let direction = match event {
+            PadEvent::Connected => {
+                self.player(pad);
+                return None;
+            }
+            PadEvent::Disconnected => {
+                for slot in &mut self.players {
+                    if *slot == Some(pad) {
+                        *slot = None;
+                    }
+                }
+                self.sticks.remove(&pad);
+                return None;
+            }
+            PadEvent::StickX(_) | PadEvent::StickY(_) => self.stick_turn(pad, event)?,
+            PadEvent::DPad(direction) => direction,
+            PadEvent::Start => return Some(Action::Pause),
+        };
+        Some(Action::Turn {
+            player: self.player(pad),
+            direction,
+        })
+    }
+
+    /// A held stick reports a stream of axis events; only the move into a new
+    /// direction turns the snake, so holding it doesn't flood the input queue.
+    fn stick_turn(&mut self, pad: usize, event: PadEvent) -> Option<Direction> {
+        let stick = self.sticks.entry(pad).or_default();
+        match event {
+            PadEvent::StickX(x) => stick.x = x,
+            PadEvent::StickY(y) => stick.y = y,
+            _ => return None,
+        }
+        let direction = stick_direction(stick.x, stick.y, DEADZONE);
+        if direction == stick.direction {
+            return None;
+        }
+        stick.direction = direction;
+        direction
+    }
+}
+
+#[cfg(feature = "gamepad")]
+mod backend {
+    use gilrs::{Axis, Button, EventType, Gilrs};
+    use rust_snake::game::Direction;
+
+    use super::PadEvent;
+
+    pub static ENABLED: bool = true;
+
+    pub struct Source {
+        gilrs: Gilrs,
+    }
+
+    impl Source {
+        pub fn open() -> Result<Source, String> {
+            let gilrs = Gilrs::new().map_err(|err| err.to_string())?;
+            Ok(Source { gilrs })
+        }
+
+        /// Ids of the pads already plugged in, in the order they were found.
+        pub fn connected(&self) -> Vec<usize> {
+            self.gilrs.gamepads().map(|(id, _)| id.into()).collect()
+        }
+
+        pub fn next_event(&mut self) -> Option<(usize, PadEvent)> {
+            while let Some(event) = self.gilrs.next_event() {
+                let pad_event = match event.event {
+                    EventType::Connected => PadEvent::Connected,
+                    EventType::Disconnected => PadEvent::Disconnected,
+                    EventType::AxisChanged(Axis::LeftStickX, value, _) => {
+                        PadEvent::StickX(value as f64)
+                    }
+                    // gilrs counts the stick's y upwards.
+                    EventType::AxisChanged(Axis::LeftStickY, value, _) => {
+                        PadEvent::StickY(-value as f64)
+                    }
+                    EventType::ButtonPressed(button, _) => match button {
+                        Button::DPadUp => PadEvent::DPad(Direction::Up),
+                        Button::DPadDown => PadEvent::DPad(Direction::Down),
+                        Button::DPadLeft => PadEvent::DPad(Direction::Left),
+                        Button::DPadRight => PadEvent::DPad(Direction::Right),
+                        Button::Start => PadEvent::Start,
+                        _ => continue,
+                    },
+                    _ => continue,
+                };
+                return Some((event.id.into(), pad_event));
+            }
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "gamepad"))]
+mod backend {
+    use super::PadEvent;
+
+    pub static ENABLED: bool = false;
+
+    /// Never constructed: without the `gamepad` feature there are no pads.
+    pub enum Source {}
+
+    impl Source {
+        pub fn open() -> Result<Source, String> {
+            Err(String::from("built without the gamepad feature"))
+        }
+
+        pub fn connected(&self) -> Vec<usize> {
+            match *self {}
+        }
+
+        pub fn next_event(&mut self) -> Option<(usize, PadEvent)> {
+            match *self {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn turn(player: usize, direction: Direction) -> Option<Action> {
+        Some(Action::Turn { player, direction })
+    }
+
+    #[test]
+    fn stick_direction_uses_deadzone_and_dominant_axis() {
+        assert_eq!(stick_direction(0.1, -0.2, DEADZONE), None);
+        assert_eq!(stick_direction(0.9, 0.5, DEADZONE), Some(Direction::Right));
+        assert_eq!(stick_direction(-0.5, -0.8, DEADZONE), Some(Direction::Up));
+        assert_eq!(stick_direction(0.0, 0.45, DEADZONE), Some(Direction::Down));
+    }
+
+    #[test]
+    fn held_stick_turns_once_per_direction() {
+        let mut gamepads = Gamepads::default();
+        assert_eq!(
+            gamepads.handle(3, PadEvent::StickX(-0.9)),
+            turn(0, Direction::Left)
+        );
+        assert_eq!(gamepads.handle(3, PadEvent::StickX(-1.0)), None);
+        assert_eq!(gamepads.handle(3, PadEvent::StickX(0.0)), None);
+        assert_eq!(
+            gamepads.handle(3, PadEvent::StickX(-0.9)),
+            turn(0, Direction::Left)
+        );
+    }
+
+    #[test]
+    fn pads_steer_players_in_connection_order() {
+        let mut gamepads = Gamepads::default();
+        assert_eq!(gamepads.handle(7, PadEvent::Connected), None);
+        assert_eq!(gamepads.handle(2, PadEvent::Connected), None);
+        assert_eq!(
+            gamepads.handle(2, PadEvent::DPad(Direction::Up)),
+            turn(1, Direction::Up)
+        );
+        assert_eq!(gamepads.handle(7, PadEvent::Start), Some(Action::Pause));
+
+        // Reconnecting gives the first pad a new id, but the same player.
+        assert_eq!(gamepads.handle(7, PadEvent::Disconnected), None);
+        assert_eq!(gamepads.handle(9, PadEvent::Connected), None);
+        assert_eq!(
+            gamepads.handle(9, PadEvent::DPad(Direction::Left)),
+            turn(0, Direction::Left)
+        );
+    }
+}
@@ -0,0 +1,255 @@
+//! Random levels. A generator lays out walls in one of a few styles, then keeps
+//! only as many as the density asks for that leave every open cell reachable.
+
+use crate::game::Position;
+use crate::level::Level;
+use crate::obstacle::Obstacle;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Rows kept clear at the top and bottom, where the snakes start, so they
+/// have room to turn before meeting a wall.
+pub static START_MARGIN: usize = 2;
+/// Walls never cover more than this fraction of the board.
+pub static MAX_DENSITY: f64 = 0.4;
+/// Board size for generated levels when none is given; the default open
+/// board is too small for rooms.
+pub static DEFAULT_ROWS: usize = 16;
+pub static DEFAULT_COLS: usize = 20;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Pattern {
+    /// Walls dividing the board into rooms joined by doorways.
+    Rooms,
+    /// A maze of one-cell corridors.
+    Corridors,
+    /// Scattered walls mirrored left to right and top to bottom.
+    Symmetric,
+}
+
+impl Pattern {
+    pub fn parse(name: &str) -> Option<Pattern> {
+        match name {
+            "rooms" => Some(Pattern::Rooms),
+            "corridors" => Some(Pattern::Corridors),
+            "symmetric" => Some(Pattern::Symmetric),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pattern::Rooms => "rooms",
+            Pattern::Corridors => "corridors",
+            Pattern::Symmetric => "symmetric",
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Generator {
+    pub rows: usize,
+    pub cols: usize,
+    pub pattern: Pattern,
+    /// Fraction of the board to wall off, up to `MAX_DENSITY`. Fewer walls
+    /// are placed if more would cut the board apart.
+    pub density: f64,
+    pub seed: u64,
+}
+
+impl Generator {
+    /// Cells between the start margins, where walls may go.
+    fn inner_rows(&self) -> std::ops::Range<usize> {
+        START_MARGIN..self.rows.saturating_sub(START_MARGIN)
+    }
+
+    /// Groups of cells to wall off together, in the order they are tried.
+    fn candidates(&self, rng: &mut ChaCha8Rng) -> Vec<Vec<Position>> {
+        let cell = |row: usize, column: usize| vec![Position::new(row as i32, column as i32)];
+        let mut groups = Vec::new();
+        match self.pattern {
+            Pattern::Rooms => {
+                let room = rng.gen_range(4..=6);
+                for row in self.inner_rows() {
+                    for column in 0..self.cols {
+                        if (row - START_MARGIN) % room == room - 1 || column % room == room - 1 {
+                            groups.push(cell(row, column));
+                        }
+                    }
+                }
+                groups.shuffle(rng);
+            }
+            Pattern::Corridors => {
+                // Walls are every cell a depth-first maze on the odd cells
+                // doesn't carve out.
+                let inner = self.inner_rows();
+                let mut open = vec![vec![false; self.cols]; self.rows];
+                let rooms: Vec<(usize, usize)> = inner
+                    .clone()
+                    .filter(|row| (row - inner.start) % 2 == 1)
+                    .flat_map(|row| (1..self.cols).step_by(2).map(move |column| (row, column)))
+                    .filter(|(row, _)| *row + 1 < inner.end)
+                    .collect();
+                if let Some(start) = rooms.choose(rng) {
+                    let mut stack = vec![*start];
+                    open[start.0][start.1] = true;
+                    while let Some(&(row, column)) = stack.last() {
+                        let mut next: Vec<(usize, usize, usize, usize)> = [
+                            (row as i32 - 2, column as i32),
+                            (row as i32 + 2, column as i32),
+                            (row as i32, column as i32 - 2),
+                            (row as i32, column as i32 + 2),
+                        ]
+                        .into_iter()
+                        .filter(|(r, c)| *r >= 0 && *c >= 0)
+                        .map(|(r, c)| (r as usize, c as usize))
+                        .filter(|next| rooms.contains(next) && !open[next.0][next.1])
+                        .map(|(r, c)| (r, c, (r + row) / 2, (c + column) / 2))
+                        .collect();
+                        next.shuffle(rng);
+                        match next.first() {
+                            Some(&(r, c, wall_row, wall_column)) => {
+                                open[wall_row][wall_column] = true;
+                                open[r][c] = true;
+                                stack.push((r, c));
+                            }
+                            None => {
+                                stack.pop();
+                            }
+                        }
+                    }
+                }
+                for row in self.inner_rows() {
+                    for (column, open) in open[row].iter().enumerate() {
+                        if !open {
+                            groups.push(cell(row, column));
+                        }
+                    }
+                }
+                groups.shuffle(rng);
+            }
+            Pattern::Symmetric => {
+                let last_row = self.rows - 1;
+                let last_col = self.cols - 1;
+                for row in self.inner_rows().filter(|row| *row <= last_row - row) {
+                    for column in (0..self.cols).filter(|column| *column <= last_col - column) {
+                        let mut group = vec![
+                            Position::new(row as i32, column as i32),
+                            Position::new(row as i32, (last_col - column) as i32),
+                            Position::new((last_row - row) as i32, column as i32),
+                            Position::new((last_row - row) as i32, (last_col - column) as i32),
+                        ];
+                        group.sort_by_key(|position| (position.get_row(), position.get_column()));
+                        group.dedup();
+                        groups.push(group);
+                    }
+                }
+                groups.shuffle(rng);
+            }
+        }
+        groups
+    }
+
+    /// Builds the level, or fails for a board without cells. The same
+    /// generator always builds the same level.
+    pub fn generate(&self) -> Result<Level, &'static str> {
+        if self.rows == 0 || self.cols == 0 {
+            return Err("A generated board needs at least one row and one column");
+        }
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        let mut level = Level {
+            name: format!("{} {}", self.pattern.name(), self.seed),
+            rows: self.rows,
+            cols: self.cols,
+            ..Level::default()
+        };
+        let target = (self.rows * self.cols) as f64 * self.density.clamp(0.0, MAX_DENSITY);
+        let target = target.round() as usize;
+        for group in self.candidates(&mut rng) {
+            if level.obstacles.len() + group.len() > target {
+                continue;
+            }
+            let count = level.obstacles.len();
+            level.obstacles.extend(
+                group
+                    .into_iter()
+                    .map(|position| Obstacle::Block { position }),
+            );
+            if !level.open_cells_connected() {
+                level.obstacles.truncate(count);
+            }
+        }
+        Ok(level)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn generator(pattern: Pattern) -> Generator {
+        Generator {
+            rows: 16,
+            cols: 20,
+            pattern,
+            density: 0.3,
+            seed: 7,
+        }
+    }
+
+    #[test]
+    fn generated_levels_are_connected_and_keep_start_rows_clear() {
+        for pattern in [Pattern::Rooms, Pattern::Corridors, Pattern::Symmetric] {
+            let level = generator(pattern).generate().unwrap();
+            assert!(level.open_cells_connected(), "{pattern:?}");
+            assert!(!level.obstacles.is_empty(), "{pattern:?}");
+            assert!(level.obstacles.len() <= 96, "{pattern:?}");
+            for obstacle in &level.obstacles {
+                let row = obstacle.reach()[0].get_row() as usize;
+                assert!((START_MARGIN..16 - START_MARGIN).contains(&row));
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_same_level() {
+        let first = generator(Pattern::Rooms).generate();
+        assert_eq!(first, generator(Pattern::Rooms).generate());
+        assert!(first.is_ok());
+        let other = Generator {
+            seed: 8,
+            ..generator(Pattern::Rooms)
+        };
+        assert_ne!(first, other.generate());
+    }
+
+    #[test]
+    fn empty_boards_are_rejected() {
+        for pattern in [Pattern::Rooms, Pattern::Corridors, Pattern::Symmetric] {
+            let no_rows = Generator {
+                rows: 0,
+                ..generator(pattern)
+            };
+            assert!(no_rows.generate().is_err(), "{pattern:?}");
+            let no_cols = Generator {
+                cols: 0,
+                ..generator(pattern)
+            };
+            assert!(no_cols.generate().is_err(), "{pattern:?}");
+        }
+    }
+
+    #[test]
+    fn symmetric_levels_are_mirrored() {
+        let level = generator(Pattern::Symmetric).generate().unwrap();
+        for obstacle in &level.obstacles {
+            let cell = obstacle.reach()[0];
+            let mirrored = Obstacle::Block {
+                position: Position::new(15 - cell.get_row(), 19 - cell.get_column()),
+            };
+            assert!(level.obstacles.contains(&mirrored));
+        }
+    }
+}
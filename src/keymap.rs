@@ -0,0 +1,514 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rust_snake::game::{Direction, Game, Snake, Turn};
+use rust_snake::storage::{data_dir, write_atomically};
+use rust_snake::topology::Topology;
+
+pub static PRESETS: [&str; 3] = ["default", "wasd", "vi"];
+
+/// What a key does. Keys are named the way piston names them ("Up", "W",
+/// "Space", "Escape", "F1"), and the terminal frontend translates its own
+/// key codes to the same names, so one keymap file serves both.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Action {
+    /// `player` counts from 0. Keymap files may also bind diagonals
+    /// ("upleft", "p2-downright"), which only grids with diagonals use.
+    Turn {
+        player: usize,
+        direction: Direction,
+    },
+    Pause,
+    Restart,
+    Quit,
+    CycleTheme,
+    Mute,
+    Settings,
+}
+
+/// Actions in the order the settings screen lists them.
+pub static ACTIONS: [Action; 14] = [
+    turn(0, Direction::Up),
+    turn(0, Direction::Down),
+    turn(0, Direction::Left),
+    turn(0, Direction::Right),
+    turn(1, Direction::Up),
+    turn(1, Direction::Down),
+    turn(1, Direction::Left),
+    turn(1, Direction::Right),
+    Action::Pause,
+    Action::Restart,
+    Action::Quit,
+    Action::CycleTheme,
+    Action::Mute,
+    Action::Settings,
+];
+
+const fn turn(player: usize, direction: Direction) -> Action {
+    Action::Turn { player, direction }
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Left => "left",
+        Direction::Up => "up",
+        Direction::Right => "right",
+        Direction::Down => "down",
+        Direction::UpLeft => "upleft",
+        Direction::UpRight => "upright",
+        Direction::DownLeft => "downleft",
+        Direction::DownRight => "downright",
+    }
+}
+
+impl Action {
+    /// Name used in keymap files, e.g. "up" for player 1 and "p2-up" for player 2.
+    pub fn name(&self) -> String {
+        match self {
+            Action::Turn {
+                player: 0,
+                direction,
+            } => direction_name(*direction).to_string(),
+            Action::Turn { player, direction } => {
+                format!("p{}-{}", player + 1, direction_name(*direction))
+            }
+            Action::Pause => String::from("pause"),
+            Action::Restart => String::from("restart"),
+            Action::Quit => String::from("quit"),
+            Action::CycleTheme => String::from("theme"),
+            Action::Mute => String::from("mute"),
+            Action::Settings => String::from("settings"),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        let (player, direction) = match name.split_once('-') {
+            Some((player, direction)) => {
+                let player = player.strip_prefix('p')?.parse::<usize>().ok()?;
+                (player.checked_sub(1)?, direction)
+            }
+            None => (0, name),
+        };
+        let direction = match direction {
+            "left" => Direction::Left,
+            "up" => Direction::Up,
+            "right" => Direction::Right,
+            "down" => Direction::Down,
+            "upleft" => Direction::UpLeft,
+            "upright" => Direction::UpRight,
+            "downleft" => Direction::DownLeft,
+            "downright" => Direction::DownRight,
+            _ if name == direction => {
+                return match name {
+                    "pause" => Some(Action::Pause),
+                    "restart" => Some(Action::Restart),
+                    "quit" => Some(Action::Quit),
+                    "theme" => Some(Action::CycleTheme),
+                    "mute" => Some(Action::Mute),
+                    "settings" => Some(Action::Settings),
+                    _ => None,
+                }
+            }
+            _ => return None,
+        };
+        Some(Action::Turn { player, direction })
+    }
+
+    /// Label shown on the settings screen.
+    pub fn label(&self) -> String {
+        match self {
+            Action::Turn { player, direction } => {
+                format!("P{} {}", player + 1, direction_name(*direction))
+            }
+            _ => self.name(),
+        }
+    }
+}
+
+/// How turn keys steer a snake.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Controls {
+    /// Each key points the snake in its direction.
+    Absolute,
+    /// Only left and right keys count, turning relative to the heading.
+    Relative,
+}
+
+/// The heading a turn key leads to for a snake heading `heading` on
+/// `topology`, or `None` if the key does nothing under `controls`.
+pub fn steered_direction(
+    topology: &dyn Topology,
+    heading: Direction,
+    direction: Direction,
+    controls: Controls,
+) -> Option<Direction> {
+    match (controls, direction) {
+        (Controls::Absolute, direction) => topology.resolve(heading, direction),
+        (Controls::Relative, Direction::Left) => Some(topology.turn(heading, Turn::Left)),
+        (Controls::Relative, Direction::Right) => Some(topology.turn(heading, Turn::Right)),
+        (Controls::Relative, _) => None,
+    }
+}
+
+/// Feeds a turn key for `player` into `game` under `controls`.
+pub fn steer(game: &mut Game, player: usize, direction: Direction, controls: Controls) {
+    let snake = resolve_player(player, game.get_snakes().len());
+    let Some(heading) = game.get_snakes().get(snake).map(Snake::queued_heading) else {
+        return;
+    };
+    let topology = game.get_settings().grid.topology();
+    if let Some(direction) = steered_direction(topology, heading, direction, controls) {
+        game.queue_direction(snake, direction);
+    }
+}
+
+/// A player that isn't in the game steers the first snake instead, so a single
+/// player can use either set of keys.
+pub fn resolve_player(player: usize, num_snakes: usize) -> usize {
+    if player < num_snakes {
+        player
+    } else {
+        0
+    }
+}
+
+/// On disk a keymap is a preset, `default` unless named, with any bindings
+/// under `[keys]` replacing the preset's keys for the actions they name:
+///
+/// ```toml
+/// preset = "vi"
+/// ```
+///
+/// ```toml
+/// [keys]
+/// H = "left"
+/// Space = "pause"
+/// ```
+#[derive(Serialize, Deserialize, Default)]
+struct KeymapFile {
+    preset: Option<String>,
+    #[serde(default)]
+    keys: BTreeMap<String, String>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Keymap {
+    bindings: BTreeMap<String, Action>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::preset("default").unwrap()
+    }
+}
+
+impl Keymap {
+    /// `default` steers player 1 with the arrows and player 2 with WASD,
+    /// `wasd` swaps them and `vi` uses hjkl for player 1.
+    pub fn preset(name: &str) -> Option<Keymap> {
+        let (first, second) = match name {
+            "default" => (["Up", "Down", "Left", "Right"], ["W", "S", "A", "D"]),
+            "wasd" => (["W", "S", "A", "D"], ["Up", "Down", "Left", "Right"]),
+            "vi" => (["K", "J", "H", "L"], ["Up", "Down", "Left", "Right"]),
+            _ => return None,
+        };
+        let directions = [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ];
+        let mut bindings = BTreeMap::new();
+        for (player, keys) in [first, second].iter().enumerate() {
+            for (key, direction) in keys.iter().zip(directions) {
+                bindings.insert(key.to_string(), turn(player, direction));
+            }
+        }
+        let actions = [
+            ("P", Action::Pause),
+            ("R", Action::Restart),
+            ("Escape", Action::Quit),
+            ("Q", Action::Quit),
+            ("T", Action::CycleTheme),
+            ("M", Action::Mute),
+            ("F1", Action::Settings),
+        ];
+        for (key, action) in actions {
+            bindings.insert(key.to_string(), action);
+        }
+        Some(Keymap { bindings })
+    }
+
+    pub fn action(&self, key: &str) -> Option<Action> {
+        self.bindings.get(key).copied()
+    }
+
+    pub fn keys_for(&self, action: Action) -> Vec<&str> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| **bound == action)
+            .map(|(key, _)| key.as_str())
+            .collect()
+    }
+
+    /// Makes `key` the only key for `action`, taking it away from whatever
+    /// action it did before.
+    pub fn bind(&mut self, key: &str, action: Action) {
+        self.bindings.retain(|_, bound| *bound != action);
+        self.bindings.insert(key.to_string(), action);
+    }
+
+    pub fn parse(contents: &str) -> Result<Keymap, String> {
+        let file: KeymapFile = toml::from_str(contents).map_err(|err| err.to_string())?;
+        let preset = file.preset.as_deref().unwrap_or("default");
+        let mut keymap =
+            Keymap::preset(preset).ok_or(format!("Unknown keymap preset: {preset}"))?;
+        let mut overrides = BTreeMap::new();
+        for (key, name) in file.keys {
+            let action = Action::from_name(&name).ok_or(format!("Unknown action: {name}"))?;
+            overrides.insert(key, action);
+        }
+        keymap
+            .bindings
+            .retain(|_, action| !overrides.values().any(|overridden| overridden == action));
+        keymap.bindings.extend(overrides);
+        Ok(keymap)
+    }
+
+    pub fn to_toml(&self) -> String {
+        let file = KeymapFile {
+            preset: None,
+            keys: self
+                .bindings
+                .iter()
+                .map(|(key, action)| (key.clone(), action.name()))
+                .collect(),
+        };
+        toml::to_string(&file).unwrap()
+    }
+
+    /// Loads the keymap at `path`, falling back to the default one when the
+    /// file is missing or invalid.
+    pub fn load(path: &Path) -> Keymap {
+        match fs::read_to_string(path) {
+            Ok(contents) => Keymap::parse(&contents).unwrap_or_else(|err| {
+                eprintln!("Invalid keymap {}. Using default keys.", path.display());
+                eprintln!("{err}");
+                Keymap::default()
+            }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Keymap::default(),
+            Err(err) => {
+                eprintln!("Failed to read keymap {}.", path.display());
+                eprintln!("{err}");
+                Keymap::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        write_atomically(path, self.to_toml().as_bytes())
+    }
+}
+
+pub fn default_keymap_path() -> PathBuf {
+    data_dir().join("keys.toml")
+}
+
+/// State of the screen for rebinding keys: a list of actions with the
+/// selected one highlighted. Enter starts rebinding the selection and the next
+/// key pressed is bound to it; Escape cancels rebinding or closes the screen.
+#[derive(PartialEq, Debug, Default)]
+pub struct SettingsScreen {
+    selected: usize,
+    rebinding: bool,
+}
+
+impl SettingsScreen {
+    pub fn new() -> SettingsScreen {
+        SettingsScreen::default()
+    }
+
+    /// Handles a key press. Returns false once the screen should close.
+    pub fn handle_key(&mut self, key: &str, keymap: &mut Keymap) -> bool {
+        if self.rebinding {
+            if key != "Escape" {
+                keymap.bind(key, ACTIONS[self.selected]);
+            }
+            self.rebinding = false;
+            return true;
+        }
+        match key {
+            "Up" => self.selected = (self.selected + ACTIONS.len() - 1) % ACTIONS.len(),
+            "Down" => self.selected = (self.selected + 1) % ACTIONS.len(),
+            "Return" => self.rebinding = true,
+            "Escape" => return false,
+            _ => (),
+        }
+        true
+    }
+
+    /// One line per action, with the selected line marked.
+    pub fn lines(&self, keymap: &Keymap) -> Vec<String> {
+        let mut lines = vec![String::from("KEYS - ENTER: CHANGE  ESC: DONE")];
+        for (index, action) in ACTIONS.iter().enumerate() {
+            let keys = if self.rebinding && index == self.selected {
+                String::from("PRESS A KEY")
+            } else {
+                keymap.keys_for(*action).join(", ")
+            };
+            let marker = if index == self.selected { ">" } else { " " };
+            lines.push(format!("{marker} {}: {keys}", action.label()).to_uppercase());
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rust_snake::game::Position;
+    use rust_snake::topology::Grid;
+
+    #[test]
+    fn action_names_round_trip() {
+        for action in ACTIONS {
+            assert_eq!(Action::from_name(&action.name()), Some(action));
+        }
+        assert_eq!(Action::from_name("p2-jump"), None);
+        assert_eq!(Action::from_name("p0-up"), None);
+        assert_eq!(Action::from_name("dance"), None);
+    }
+
+    #[test]
+    fn default_preset_arrows_and_wasd() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.action("Up"), Some(turn(0, Direction::Up)));
+        assert_eq!(keymap.action("A"), Some(turn(1, Direction::Left)));
+        assert_eq!(keymap.action("Escape"), Some(Action::Quit));
+        assert_eq!(keymap.action("X"), None);
+    }
+
+    #[test]
+    fn vi_preset_uses_hjkl() {
+        let keymap = Keymap::preset("vi").unwrap();
+        assert_eq!(keymap.action("H"), Some(turn(0, Direction::Left)));
+        assert_eq!(keymap.action("J"), Some(turn(0, Direction::Down)));
+        assert_eq!(keymap.action("K"), Some(turn(0, Direction::Up)));
+        assert_eq!(keymap.action("L"), Some(turn(0, Direction::Right)));
+        assert!(Keymap::preset("emacs").is_none());
+    }
+
+    #[test]
+    fn second_player_keys_steer_only_snake() {
+        assert_eq!(resolve_player(1, 2), 1);
+        assert_eq!(resolve_player(1, 1), 0);
+    }
+
+    #[test]
+    fn bind_replaces_previous_keys() {
+        let mut keymap = Keymap::default();
+        keymap.bind("Space", Action::Pause);
+        keymap.bind("Up", Action::Restart);
+        assert_eq!(keymap.keys_for(Action::Pause), vec!["Space"]);
+        assert_eq!(keymap.keys_for(Action::Restart), vec!["Up"]);
+        assert!(keymap.keys_for(turn(0, Direction::Up)).is_empty());
+    }
+
+    #[test]
+    fn parse_preset_and_bindings() {
+        assert_eq!(
+            Keymap::parse("preset = \"wasd\"").unwrap(),
+            Keymap::preset("wasd").unwrap()
+        );
+        // Bindings replace the preset's keys for the actions they name only.
+        let keymap = Keymap::parse("[keys]\nSpace = \"pause\"\nI = \"p2-up\"").unwrap();
+        assert_eq!(keymap.action("I"), Some(turn(1, Direction::Up)));
+        assert_eq!(keymap.action("W"), None);
+        assert_eq!(keymap.keys_for(Action::Pause), vec!["Space"]);
+        assert_eq!(keymap.action("Up"), Some(turn(0, Direction::Up)));
+        assert_eq!(keymap.action("Escape"), Some(Action::Quit));
+        let keymap = Keymap::parse("preset = \"vi\"\n[keys]\nQ = \"quit\"").unwrap();
+        assert_eq!(keymap.action("H"), Some(turn(0, Direction::Left)));
+        assert_eq!(keymap.keys_for(Action::Quit), vec!["Q"]);
+        assert!(Keymap::parse("[keys]\nX = \"fly\"").is_err());
+        assert!(Keymap::parse("preset = \"emacs\"").is_err());
+
+        let keymap = Keymap::preset("vi").unwrap();
+        assert_eq!(Keymap::parse(&keymap.to_toml()).unwrap(), keymap);
+    }
+
+    #[test]
+    fn settings_screen_rebinds_selected_action() {
+        let mut keymap = Keymap::default();
+        let mut screen = SettingsScreen::new();
+        assert!(screen.handle_key("Down", &mut keymap));
+        assert!(screen.handle_key("Return", &mut keymap));
+        assert!(screen.lines(&keymap)[2].contains("PRESS A KEY"));
+        assert!(screen.handle_key("N", &mut keymap));
+        assert_eq!(keymap.keys_for(turn(0, Direction::Down)), vec!["N"]);
+
+        assert!(screen.handle_key("Return", &mut keymap));
+        assert!(screen.handle_key("Escape", &mut keymap));
+        assert_eq!(keymap.keys_for(turn(0, Direction::Down)), vec!["N"]);
+        assert!(!screen.handle_key("Escape", &mut keymap));
+    }
+
+    #[test]
+    fn relative_controls_ignore_up_and_down() {
+        let square = Grid::Square.topology();
+        assert_eq!(
+            steered_direction(square, Direction::Up, Direction::Left, Controls::Relative),
+            Some(Direction::Left)
+        );
+        assert_eq!(
+            steered_direction(
+                square,
+                Direction::Left,
+                Direction::Right,
+                Controls::Relative
+            ),
+            Some(Direction::Up)
+        );
+        assert_eq!(
+            steered_direction(square, Direction::Left, Direction::Down, Controls::Relative),
+            None
+        );
+        assert_eq!(
+            steered_direction(square, Direction::Left, Direction::Down, Controls::Absolute),
+            Some(Direction::Down)
+        );
+    }
+
+    #[test]
+    fn relative_turns_build_on_each_other() {
+        let snake = Snake::with_length(Position::new(4, 4), Direction::Right, 3);
+        let mut game = Game::new_multiplayer(10, 10, vec![snake], Position::new(0, 0));
+        steer(&mut game, 0, Direction::Right, Controls::Relative);
+        steer(&mut game, 0, Direction::Right, Controls::Relative);
+        assert!(game.update_game().is_ok());
+        assert_eq!(game.get_current_direction(), Direction::Down);
+        assert!(game.update_game().is_ok());
+        assert_eq!(game.get_current_direction(), Direction::Left);
+    }
+
+    #[test]
+    fn turn_keys_follow_the_grid() {
+        let hex = Grid::Hex.topology();
+        assert_eq!(
+            steered_direction(hex, Direction::Left, Direction::Right, Controls::Relative),
+            Some(Direction::UpLeft)
+        );
+        assert_eq!(
+            steered_direction(hex, Direction::Left, Direction::Down, Controls::Absolute),
+            Some(Direction::DownLeft)
+        );
+        assert_eq!(
+            Action::from_name("p2-downright"),
+            Some(turn(1, Direction::DownRight))
+        );
+    }
+}
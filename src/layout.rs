@@ -0,0 +1,251 @@
+use rust_snake::game::Position;
+use rust_snake::topology::Grid;
+
+/// Where the board sits on screen. Cells are always square, or hexagons as
+/// wide as a square and a third taller; the board is scaled to fit the
+/// available area and centered, leaving bars on the sides that don't match
+/// the board's aspect ratio.
+///
+/// Coordinates are in window units. `scale` is the number of physical pixels
+/// per window unit (2.0 on a typical HiDPI display); cell size and origin are
+/// snapped to whole physical pixels so every cell edge stays crisp.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct BoardLayout {
+    pub x: f64,
+    pub y: f64,
+    pub cell_size: f64,
+    pub num_rows: usize,
+    pub num_cols: usize,
+    pub grid: Grid,
+}
+
+/// How many cells wider and taller than its rows and columns a board is.
+/// Hex boards shift every odd row half a cell right, and their top and
+/// bottom rows poke out a sixth of a cell.
+fn overhang(grid: Grid) -> (f64, f64) {
+    match grid {
+        Grid::Hex => (0.5, 1.0 / 3.0),
+        _ => (0.0, 0.0),
+    }
+}
+
+impl BoardLayout {
+    /// Fits a `num_rows` x `num_cols` board into `area` (`[x, y, width, height]`).
+    pub fn fit(
+        area: [f64; 4],
+        num_rows: usize,
+        num_cols: usize,
+        grid: Grid,
+        scale: f64,
+    ) -> BoardLayout {
+        let [area_x, area_y, area_width, area_height] = area;
+        let (extra_width, extra_height) = overhang(grid);
+        let cell_size = (area_width / (num_cols.max(1) as f64 + extra_width))
+            .min(area_height / (num_rows.max(1) as f64 + extra_height))
+            .max(0.0);
+        let cell_size = snap_down(cell_size, scale);
+        let board_width = cell_size * (num_cols as f64 + extra_width);
+        let board_height = cell_size * (num_rows as f64 + extra_height);
+        BoardLayout {
+            x: snap(area_x + (area_width - board_width) / 2.0, scale),
+            y: snap(area_y + (area_height - board_height) / 2.0, scale),
+            cell_size,
+            num_rows,
+            num_cols,
+            grid,
+        }
+    }
+
+    /// Screen rectangle `[x, y, width, height]` of the cell at `position`.
+    /// On hex boards this is the square the cell's hexagon is centered on.
+    pub fn cell_rect(&self, position: Position) -> [f64; 4] {
+        let (shift, top) = match self.grid {
+            Grid::Hex => (
+                position.get_row().rem_euclid(2) as f64 / 2.0,
+                self.cell_size / 6.0,
+            ),
+            _ => (0.0, 0.0),
+        };
+        [
+            self.x + (position.get_column() as f64 + shift) * self.cell_size,
+            self.y + top + position.get_row() as f64 * self.cell_size,
+            self.cell_size,
+            self.cell_size,
+        ]
+    }
+
+    /// Corners of the hexagon drawn for the cell at `position`, pointing up
+    /// and down, `inset` window units inside the cell's edges. Hexagons are a
+    /// third taller than `cell_rect` so that neighbouring rows interlock.
+    pub fn hexagon(&self, position: Position, inset: f64) -> [[f64; 2]; 6] {
+        let [x, y, size, _] = self.cell_rect(position);
+        let center = [x + size / 2.0, y + size / 2.0];
+        // Corners of a unit cell, scaled down around the center by the inset.
+        let factor = ((size - 2.0 * inset) / size).max(0.0);
+        [
+            [0.0, -2.0 / 3.0],
+            [0.5, -1.0 / 3.0],
+            [0.5, 1.0 / 3.0],
+            [0.0, 2.0 / 3.0],
+            [-0.5, 1.0 / 3.0],
+            [-0.5, -1.0 / 3.0],
+        ]
+        .map(|[dx, dy]| {
+            [
+                center[0] + dx * size * factor,
+                center[1] + dy * size * factor,
+            ]
+        })
+    }
+
+    pub fn board_rect(&self) -> [f64; 4] {
+        let (extra_width, extra_height) = overhang(self.grid);
+        [
+            self.x,
+            self.y,
+            self.cell_size * (self.num_cols as f64 + extra_width),
+            self.cell_size * (self.num_rows as f64 + extra_height),
+        ]
+    }
+}
+
+/// Splits the window into a HUD strip one cell tall above a letterboxed board,
+/// scaled together so the HUD grows and shrinks with the cells.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct ScreenLayout {
+    pub hud: [f64; 4],
+    pub board: BoardLayout,
+}
+
+impl ScreenLayout {
+    pub fn fit(
+        area: [f64; 4],
+        num_rows: usize,
+        num_cols: usize,
+        grid: Grid,
+        scale: f64,
+    ) -> ScreenLayout {
+        let content = BoardLayout::fit(area, num_rows + 1, num_cols, grid, scale);
+        let cell_size = content.cell_size;
+        ScreenLayout {
+            hud: [content.x, content.y, content.board_rect()[2], cell_size],
+            board: BoardLayout {
+                y: content.y + cell_size,
+                num_rows,
+                ..content
+            },
+        }
+    }
+
+    /// Window size that shows the board and HUD without any bars.
+    pub fn window_size(num_rows: usize, num_cols: usize, grid: Grid, cell_size: f64) -> [f64; 2] {
+        let (extra_width, extra_height) = overhang(grid);
+        [
+            (num_cols as f64 + extra_width) * cell_size,
+            ((num_rows + 1) as f64 + extra_height) * cell_size,
+        ]
+    }
+}
+
+/// Rounds `value` to the nearest whole physical pixel.
+pub fn snap(value: f64, scale: f64) -> f64 {
+    if scale > 0.0 {
+        (value * scale).round() / scale
+    } else {
+        value
+    }
+}
+
+/// Rounds `value` down to a whole number of physical pixels.
+pub fn snap_down(value: f64, scale: f64) -> f64 {
+    if scale > 0.0 {
+        (value * scale).floor() / scale
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn fit_square_board_in_square_window() {
+        let layout = BoardLayout::fit([0.0, 0.0, 500.0, 500.0], 10, 10, Grid::Square, 1.0);
+        assert_eq!(layout.cell_size, 50.0);
+        assert_eq!(layout.board_rect(), [0.0, 0.0, 500.0, 500.0]);
+    }
+
+    #[test]
+    fn fit_wide_board_letterboxes_vertically() {
+        let layout = BoardLayout::fit([0.0, 0.0, 400.0, 400.0], 5, 20, Grid::Square, 1.0);
+        assert_eq!(layout.cell_size, 20.0);
+        assert_eq!(layout.board_rect(), [0.0, 150.0, 400.0, 100.0]);
+    }
+
+    #[test]
+    fn fit_square_board_in_wide_window_pillarboxes() {
+        let layout = BoardLayout::fit([0.0, 0.0, 800.0, 400.0], 10, 10, Grid::Square, 1.0);
+        assert_eq!(layout.cell_size, 40.0);
+        assert_eq!(layout.board_rect(), [200.0, 0.0, 400.0, 400.0]);
+    }
+
+    #[test]
+    fn screen_layout_puts_hud_above_board() {
+        let size = ScreenLayout::window_size(10, 10, Grid::Square, 40.0);
+        assert_eq!(size, [400.0, 440.0]);
+        let layout = ScreenLayout::fit([0.0, 0.0, size[0], size[1]], 10, 10, Grid::Square, 1.0);
+        assert_eq!(layout.hud, [0.0, 0.0, 400.0, 40.0]);
+        assert_eq!(layout.board.board_rect(), [0.0, 40.0, 400.0, 400.0]);
+    }
+
+    #[test]
+    fn screen_layout_keeps_hud_attached_when_letterboxed() {
+        let layout = ScreenLayout::fit([0.0, 0.0, 400.0, 1000.0], 9, 10, Grid::Square, 1.0);
+        assert_eq!(layout.hud, [0.0, 300.0, 400.0, 40.0]);
+        assert_eq!(layout.board.board_rect(), [0.0, 340.0, 400.0, 360.0]);
+    }
+
+    #[test]
+    fn fit_snaps_to_physical_pixels() {
+        let layout = BoardLayout::fit([0.0, 0.0, 101.0, 101.0], 3, 3, Grid::Square, 2.0);
+        assert_eq!(layout.cell_size, 33.5);
+        assert_eq!(layout.x, 0.5);
+        let layout = BoardLayout::fit([0.0, 0.0, 101.0, 101.0], 3, 3, Grid::Square, 1.0);
+        assert_eq!(layout.cell_size, 33.0);
+        assert_eq!(layout.x, 1.0);
+    }
+
+    #[test]
+    fn cell_rect_uses_column_for_x_and_row_for_y() {
+        let layout = BoardLayout::fit([10.0, 20.0, 300.0, 200.0], 2, 3, Grid::Square, 1.0);
+        assert_eq!(
+            layout.cell_rect(Position::new(1, 2)),
+            [210.0, 120.0, 100.0, 100.0]
+        );
+    }
+
+    #[test]
+    fn hex_rows_shift_and_interlock() {
+        let size = ScreenLayout::window_size(3, 4, Grid::Hex, 60.0);
+        assert_eq!(size, [270.0, 260.0]);
+        let layout = ScreenLayout::fit([0.0, 0.0, size[0], size[1]], 3, 4, Grid::Hex, 1.0);
+        assert_eq!(layout.hud, [0.0, 0.0, 270.0, 60.0]);
+        let board = layout.board;
+        assert_eq!(
+            board.cell_rect(Position::new(0, 0)),
+            [0.0, 70.0, 60.0, 60.0]
+        );
+        assert_eq!(
+            board.cell_rect(Position::new(1, 0)),
+            [30.0, 130.0, 60.0, 60.0]
+        );
+        // The lower corners of a hexagon are the upper corners of the one
+        // below and to its right.
+        let upper = board.hexagon(Position::new(0, 0), 0.0);
+        let lower = board.hexagon(Position::new(1, 0), 0.0);
+        assert_eq!(upper[3], lower[5]);
+        assert_eq!(upper[2], lower[0]);
+    }
+}
@@ -0,0 +1,224 @@
+use crate::game::{Direction, Portal, Position};
+use crate::obstacle::Obstacle;
+use crate::storage::write_atomically;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+
+/// A board layout. Levels are plain TOML files; fields a file leaves out keep
+/// their default, e.g.
+///
+/// ```toml
+/// name = "Crossing"
+/// rows = 12
+/// cols = 16
+///
+/// [[obstacles]]
+/// kind = "patrol"
+/// waypoints = [{ row = 6, column = 2 }, { row = 6, column = 13 }]
+/// period = 2
+///
+/// [[obstacles]]
+/// kind = "rotating"
+/// pivot = { row = 3, column = 8 }
+/// length = 2
+///
+/// [[portals]]
+/// a = { row = 2, column = 1 }
+/// b = { row = 9, column = 14 }
+/// ```
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Level {
+    pub name: String,
+    pub rows: usize,
+    pub cols: usize,
+    pub obstacles: Vec<Obstacle>,
+    pub portals: Vec<Portal>,
+    /// Seed for food placement, so everyone playing the level gets the same
+    /// food. Without one every round is different.
+    pub seed: Option<u64>,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Level {
+            name: String::from("Open"),
+            rows: 10,
+            cols: 10,
+            obstacles: Vec::new(),
+            portals: Vec::new(),
+            seed: None,
+        }
+    }
+}
+
+impl Level {
+    /// Whether every cell off the blocks can be reached from every other.
+    /// Moving obstacles only get in the way some of the time, so they don't
+    /// count.
+    pub fn open_cells_connected(&self) -> bool {
+        let mut blocked = vec![vec![false; self.cols]; self.rows];
+        for obstacle in &self.obstacles {
+            if let Obstacle::Block { position } = obstacle {
+                if let Some(cell) = self.cell(*position) {
+                    blocked[cell.0][cell.1] = true;
+                }
+            }
+        }
+        let open = blocked
+            .iter()
+            .flatten()
+            .filter(|blocked| !**blocked)
+            .count();
+        let Some(start) = (0..self.rows * self.cols)
+            .map(|index| (index / self.cols, index % self.cols))
+            .find(|(row, column)| !blocked[*row][*column])
+        else {
+            return true;
+        };
+
+        let mut seen = vec![vec![false; self.cols]; self.rows];
+        seen[start.0][start.1] = true;
+        let mut reached = 1;
+        let mut queue = VecDeque::from([start]);
+        while let Some((row, column)) = queue.pop_front() {
+            let position = Position::new(row as i32, column as i32);
+            for direction in [
+                Direction::Left,
+                Direction::Up,
+                Direction::Right,
+                Direction::Down,
+            ] {
+                let Some(next) = self.cell(position.step(direction)) else {
+                    continue;
+                };
+                if !blocked[next.0][next.1] && !seen[next.0][next.1] {
+                    seen[next.0][next.1] = true;
+                    reached += 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+        reached == open
+    }
+
+    /// Row and column of `position`, if it is on the board.
+    fn cell(&self, position: Position) -> Option<(usize, usize)> {
+        let row = usize::try_from(position.get_row()).ok()?;
+        let column = usize::try_from(position.get_column()).ok()?;
+        (row < self.rows && column < self.cols).then_some((row, column))
+    }
+}
+
+pub fn parse_level(contents: &str) -> Result<Level, String> {
+    let level: Level = toml::from_str(contents).map_err(|err| err.to_string())?;
+    if level.rows < 2 || level.cols < 2 {
+        return Err(format!("Level is too small: {}x{}", level.rows, level.cols));
+    }
+    Ok(level)
+}
+
+pub fn load_level(path: &Path) -> Result<Level, String> {
+    let contents = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    parse_level(&contents)
+}
+
+/// Writes `level` as a level file that `load_level` reads back.
+pub fn save_level(level: &Level, path: &Path) -> Result<(), String> {
+    let contents = toml::to_string(level).map_err(|err| err.to_string())?;
+    write_atomically(path, contents.as_bytes()).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::game::Position;
+    use crate::storage::temporary_path;
+
+    #[test]
+    fn parse_level_reads_obstacles() {
+        let level = parse_level(
+            "rows = 12\n\
+             [[obstacles]]\n\
+             kind = \"block\"\n\
+             position = { row = 1, column = 2 }\n\
+             [[obstacles]]\n\
+             kind = \"patrol\"\n\
+             waypoints = [{ row = 0, column = 0 }, { row = 0, column = 3 }]\n\
+             [[portals]]\n\
+             a = { row = 1, column = 1 }\n\
+             b = { row = 8, column = 8 }\n",
+        )
+        .unwrap();
+        assert_eq!(level.rows, 12);
+        assert_eq!(level.cols, 10);
+        assert_eq!(
+            level.obstacles[0],
+            Obstacle::Block {
+                position: Position::new(1, 2)
+            }
+        );
+        assert!(matches!(
+            level.obstacles[1],
+            Obstacle::Patrol { period: 1, .. }
+        ));
+        assert_eq!(level.portals[0].b, Position::new(8, 8));
+    }
+
+    #[test]
+    fn open_cells_connected_ignores_moving_obstacles() {
+        let block = |row, column| Obstacle::Block {
+            position: Position::new(row, column),
+        };
+        let mut level = Level {
+            rows: 3,
+            cols: 3,
+            obstacles: vec![block(0, 1), block(1, 1)],
+            ..Level::default()
+        };
+        assert!(level.open_cells_connected());
+        level.obstacles.push(block(2, 1));
+        assert!(!level.open_cells_connected());
+        level.obstacles[2] = Obstacle::Patrol {
+            waypoints: vec![Position::new(2, 0), Position::new(2, 2)],
+            period: 1,
+        };
+        assert!(level.open_cells_connected());
+    }
+
+    #[test]
+    fn saved_level_loads_back() {
+        let level = Level {
+            name: String::from("Saved"),
+            obstacles: vec![
+                Obstacle::Block {
+                    position: Position::new(3, 3),
+                },
+                Obstacle::Rotating {
+                    pivot: Position::new(6, 6),
+                    length: 2,
+                    period: 3,
+                },
+            ],
+            portals: vec![Portal {
+                a: Position::new(1, 1),
+                b: Position::new(8, 8),
+            }],
+            seed: Some(99),
+            ..Level::default()
+        };
+        let path = temporary_path("level.toml");
+        save_level(&level, &path).unwrap();
+        assert_eq!(load_level(&path).unwrap(), level);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn parse_level_rejects_bad_files() {
+        assert!(parse_level("rows = 1").is_err());
+        assert!(parse_level("[[obstacles]]\nkind = \"lava\"").is_err());
+    }
+}
@@ -0,0 +1,12 @@
+pub mod daily;
+pub mod env;
+pub mod game;
+pub mod generate;
+pub mod level;
+pub mod mode;
+pub mod net;
+pub mod obstacle;
+pub mod save;
+pub mod scores;
+pub mod storage;
+pub mod topology;
@@ -1,57 +1,260 @@
 use piston_window::Button::Keyboard;
 use piston_window::*;
 use std::process;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use std::collections::VecDeque;
+use std::env;
+use std::path::Path;
 
-mod game;
-use game::Game;
+mod audio;
+mod font;
+mod gamepad;
+mod keymap;
+mod layout;
+mod options;
+mod snake_render;
+mod sprites;
+mod theme;
+mod tui;
 
-static SNAKE_COLOR: [f32; 4] = [0.2, 0.6, 0.3, 1.0];
-static FOOD_COLOR: [f32; 4] = [0.7, 0.3, 0.2, 1.0];
-static BACKGROUND_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
-static GAME_OVER_OVERLAY_COLOR: [f32; 4] = [0.8, 0.1, 0.1, 0.2];
+use audio::Audio;
+use gamepad::Gamepads;
+use keymap::{default_keymap_path, steer, steered_direction, Action, Keymap, SettingsScreen};
+use layout::{snap, snap_down, ScreenLayout};
+use options::{parse_args, Command, Frontend, Options, USAGE};
+use rust_snake::daily::{daily_level, Date};
+use rust_snake::game;
+use rust_snake::game::{Game, GameSettings, PowerUp};
+use rust_snake::generate::{Generator, DEFAULT_COLS, DEFAULT_ROWS};
+use rust_snake::level::{load_level, save_level, Level};
+use rust_snake::net::{Client, Server, Snapshot};
+use rust_snake::save::{daily_save_path, default_save_path, delete_save, load_game, save_game};
+use rust_snake::scores::{default_scores_path, HighScores};
+use rust_snake::topology::Grid;
+use sprites::{default_sheet_path, SpriteSheet};
+use theme::{Theme, Themes};
+
+static CONTINUE_PROMPT: [&str; 2] = ["C: CONTINUE SAVED GAME", "N: NEW GAME"];
+static PAUSED_PROMPT: [&str; 1] = ["PAUSED"];
 
-static GAME_UPDATE_SPEED: u128 = 250;
 static GAME_OVER_SLEEP_MS: u128 = 500;
+static RECONNECT_INTERVAL_MS: u128 = 1000;
+
+static MIN_WINDOW_SIZE: [f64; 2] = [160.0, 160.0];
+/// Board size used to size the window before a network game's board is known.
+static DEFAULT_BOARD_SIZE: usize = 10;
+
+fn mode_name(game: &Game) -> String {
+    let mode = game.get_settings().mode.name();
+    if game.get_snakes().len() > 1 {
+        format!("Two Player {mode}")
+    } else {
+        String::from(mode)
+    }
+}
+
+fn score_text(game: &Game) -> String {
+    if game.get_snakes().len() > 1 {
+        let scores: Vec<String> = game
+            .get_snakes()
+            .iter()
+            .enumerate()
+            .map(|(index, snake)| format!("P{}: {}", index + 1, snake.get_score()))
+            .collect();
+        scores.join("  ")
+    } else {
+        format!("Score: {}", game.get_score())
+    }
+}
+
+/// Milliseconds between ticks, shortened or stretched by speed items.
+fn tick_millis(game: &Game) -> u128 {
+    (game::TICK_MILLIS as f64 / game.get_speed()) as u128
+}
+
+fn power_up_name(power_up: PowerUp) -> &'static str {
+    match power_up {
+        PowerUp::Ghost => "Ghost",
+        PowerUp::Shield => "Shield",
+        PowerUp::Magnet => "Magnet",
+    }
+}
+
+/// Active power-ups with the seconds they have left, e.g. "Ghost 7s". Each is
+/// tagged with its player in multiplayer games.
+fn power_up_text(game: &Game) -> String {
+    let view = game.view();
+    let tick = tick_millis(game) as u32;
+    let mut parts = Vec::new();
+    for (index, snake) in view.snakes.iter().enumerate() {
+        for active in &snake.power_ups {
+            let seconds = (active.ticks_left * tick).div_ceil(1000);
+            let name = power_up_name(active.power_up);
+            parts.push(if view.snakes.len() > 1 {
+                format!("P{} {name} {seconds}s", index + 1)
+            } else {
+                format!("{name} {seconds}s")
+            });
+        }
+    }
+    parts.join("  ")
+}
+
+/// Game time left as "m:ss", rounded up so the clock reads 0:00 only once
+/// time is up.
+fn time_text(time_left_ms: u64) -> String {
+    let seconds = time_left_ms.div_ceil(1000);
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Scores followed by the clock in timed modes and any active power-ups.
+fn hud_text(game: &Game) -> String {
+    let mut parts = vec![score_text(game)];
+    if let Some(time_left) = game.get_time_left_ms() {
+        parts.push(format!("Time {}", time_text(time_left)));
+    }
+    let power_ups = power_up_text(game);
+    if !power_ups.is_empty() {
+        parts.push(power_ups);
+    }
+    parts.join("  ")
+}
+
+fn window_title(game: &Game) -> String {
+    format!("Rust Snake - {} - {}", mode_name(game), score_text(game))
+}
 
-fn render_game(event: Event, window: &mut PistonWindow, game: &Game, game_over: bool) {
+/// Updates the title only when it changed, since some platforms redraw the
+/// whole window decoration on every call.
+fn update_title(window: &mut PistonWindow, game: &Game) {
+    let title = window_title(game);
+    if window.get_title() != title {
+        window.set_title(title);
+    }
+}
+
+/// Grows the window back to the minimum size if the user shrank it further.
+fn enforce_min_size(window: &mut PistonWindow, event: &Event) {
+    if let Event::Input(Input::Resize(resize_args), _) = event {
+        let [width, height] = resize_args.window_size;
+        if width < MIN_WINDOW_SIZE[0] || height < MIN_WINDOW_SIZE[1] {
+            window.set_size([
+                width.max(MIN_WINDOW_SIZE[0]),
+                height.max(MIN_WINDOW_SIZE[1]),
+            ]);
+        }
+    }
+}
+
+/// Physical pixels per window unit, e.g. 2.0 on most HiDPI displays.
+fn display_scale(window: &PistonWindow) -> f64 {
+    let width = window.size().width;
+    if width > 0.0 {
+        window.draw_size().width / width
+    } else {
+        1.0
+    }
+}
+
+fn render_game(
+    event: Event,
+    window: &mut PistonWindow,
+    game: &Game,
+    game_over: bool,
+    theme: &Theme,
+    sprites: Option<&SpriteSheet>,
+) {
     let window_width = window.size().width;
     let window_height: f64 = window.size().height;
-    let draw_width_of_one_square = window.size().width / game.get_num_rows() as f64;
-    let draw_height_of_one_square = window.size().height / game.get_num_cols() as f64;
+    let scale = display_scale(window);
+    let grid = game.get_settings().grid;
+    let screen = ScreenLayout::fit(
+        [0.0, 0.0, window_width, window_height],
+        game.get_num_rows(),
+        game.get_num_cols(),
+        grid,
+        scale,
+    );
+    let layout = screen.board;
+    // Sheets only have tiles for square cells.
+    let sprites = sprites.filter(|_| grid == Grid::Square);
+    let hud_text = hud_text(game);
 
     window.draw_2d(&event, |c, g, _| {
-        clear(BACKGROUND_COLOR, g);
-        for position in game.get_snake_positions() {
-            rectangle(
-                SNAKE_COLOR,
-                [
-                    position.get_column() as f64 * draw_width_of_one_square,
-                    position.get_row() as f64 * draw_height_of_one_square,
-                    draw_width_of_one_square,
-                    draw_height_of_one_square,
-                ],
-                c.transform,
-                g,
-            );
-        }
-        let food_position = game.get_food_position();
-        rectangle(
-            FOOD_COLOR,
-            [
-                food_position.get_column() as f64 * draw_width_of_one_square,
-                food_position.get_row() as f64 * draw_height_of_one_square,
-                draw_width_of_one_square,
-                draw_height_of_one_square,
-            ],
+        clear(theme.letterbox, g);
+        rectangle(theme.hud, screen.hud, c.transform, g);
+        // Shrink the text when power-ups make it wider than the strip.
+        let fit = (screen.hud[2] / (font::text_width(&hud_text, 1.0) + 4.0)).floor();
+        let pixel = (screen.hud[3] / (font::GLYPH_HEIGHT + 3.0))
+            .floor()
+            .min(fit)
+            .max(1.0);
+        font::draw_text(
+            &hud_text,
+            screen.hud[0] + 2.0 * pixel,
+            snap(
+                screen.hud[1] + (screen.hud[3] - font::GLYPH_HEIGHT * pixel) / 2.0,
+                scale,
+            ),
+            pixel,
+            theme.hud_text,
             c.transform,
             g,
         );
+        if grid == Grid::Hex {
+            draw_hex_board(&layout, theme, 1.0 / scale, c.transform, g);
+        } else {
+            rectangle(theme.background, layout.board_rect(), c.transform, g);
+            if theme.grid[3] > 0.0 {
+                draw_grid(&layout, theme.grid, 1.0 / scale, c.transform, g);
+            }
+        }
+        for (index, snake) in game.get_snakes().iter().enumerate() {
+            match sprites {
+                Some(sheet) => sheet.draw_snake(
+                    snake,
+                    game.get_portals(),
+                    // The first snake keeps the sheet's colors.
+                    (index > 0).then(|| theme.snake_color(index)),
+                    &layout,
+                    c.transform,
+                    g,
+                ),
+                None => snake_render::draw_snake(
+                    snake,
+                    game.get_portals(),
+                    theme.snake_color(index),
+                    theme.head_color(index),
+                    &layout,
+                    c.transform,
+                    g,
+                ),
+            }
+        }
+        for (index, portal) in game.get_portals().iter().enumerate() {
+            for end in [portal.a, portal.b] {
+                ellipse(
+                    theme.portal_color(index),
+                    layout.cell_rect(end),
+                    c.transform,
+                    g,
+                );
+            }
+        }
+        for position in game.get_obstacle_cells() {
+            fill_cell(theme.walls, &layout, position, c.transform, g);
+        }
+        for food in game.get_food() {
+            let food_rect = layout.cell_rect(*food.get_position());
+            let food_color = theme.item_color(food);
+            match sprites {
+                Some(sheet) => sheet.draw_food(food, food_color, food_rect, c.transform, g),
+                None => fill_cell(food_color, &layout, *food.get_position(), c.transform, g),
+            }
+        }
         if game_over {
             rectangle(
-                GAME_OVER_OVERLAY_COLOR,
+                theme.game_over_overlay,
                 [0.0, 0.0, window_width, window_height],
                 c.transform,
                 g,
@@ -60,72 +263,643 @@ fn render_game(event: Event, window: &mut PistonWindow, game: &Game, game_over:
     });
 }
 
-fn handle_buttons(button: ButtonArgs, game: &mut Game) {
-    let inner_button = button.button;
-    if let Keyboard(key) = inner_button {
-        game.handle_key(key);
+/// Fills the cell at `position`, a square or a hexagon depending on the grid.
+fn fill_cell(
+    color: [f32; 4],
+    layout: &layout::BoardLayout,
+    position: game::Position,
+    transform: math::Matrix2d,
+    g: &mut G2d,
+) {
+    match layout.grid {
+        Grid::Hex => polygon(color, &layout.hexagon(position, 0.0), transform, g),
+        _ => rectangle(color, layout.cell_rect(position), transform, g),
     }
 }
 
-fn create_game() -> Game {
-    let num_rows = 10;
-    let num_cols = 10;
-    let mut snake_body = VecDeque::new();
-    snake_body.push_front(game::Position::new(0, 0));
-    snake_body.push_front(game::Position::new(0, 1));
-    snake_body.push_front(game::Position::new(0, 2));
-    let snake_direction = game::Direction::Right;
-    let food_position = game::Position::new(5, 5);
+/// Draws every hexagon in the background color, inset to leave grid lines
+/// `width` window units thick between them when the theme shows the grid.
+fn draw_hex_board(
+    layout: &layout::BoardLayout,
+    theme: &Theme,
+    width: f64,
+    transform: math::Matrix2d,
+    g: &mut G2d,
+) {
+    let show_grid = theme.grid[3] > 0.0;
+    for row in 0..layout.num_rows as i32 {
+        for column in 0..layout.num_cols as i32 {
+            let position = game::Position::new(row, column);
+            if show_grid {
+                fill_cell(theme.grid, layout, position, transform, g);
+            }
+            let inset = if show_grid { width / 2.0 } else { 0.0 };
+            polygon(
+                theme.background,
+                &layout.hexagon(position, inset),
+                transform,
+                g,
+            );
+        }
+    }
+}
 
-    Game::new(
-        num_rows,
-        num_cols,
-        snake_body,
-        snake_direction,
-        food_position,
-    )
+/// Draws the lines between cells, `width` window units thick.
+fn draw_grid(
+    layout: &layout::BoardLayout,
+    color: [f32; 4],
+    width: f64,
+    transform: math::Matrix2d,
+    g: &mut G2d,
+) {
+    let [x, y, board_width, board_height] = layout.board_rect();
+    for column in 1..layout.num_cols {
+        let line_x = x + column as f64 * layout.cell_size;
+        rectangle(color, [line_x, y, width, board_height], transform, g);
+    }
+    for row in 1..layout.num_rows {
+        let line_y = y + row as f64 * layout.cell_size;
+        rectangle(color, [x, line_y, board_width, width], transform, g);
+    }
+}
+
+fn pressed_key(event: &Event) -> Option<Key> {
+    if let Event::Input(Input::Button(button_args), _) = event {
+        if let (Keyboard(key), ButtonState::Press) = (button_args.button, button_args.state) {
+            return Some(key);
+        }
+    }
+    None
+}
+
+/// Name of `key` as used in keymap files; piston's own names, e.g. "Up" or "Space".
+fn key_name(key: Key) -> String {
+    format!("{key:?}")
+}
+
+/// The keymap chosen with `--keys`, otherwise the user's keymap file.
+fn load_keymap(options: &Options) -> Keymap {
+    match &options.keys {
+        Some(preset) => Keymap::preset(preset).unwrap_or_default(),
+        None => Keymap::load(&default_keymap_path()),
+    }
+}
+
+/// The level asked for with `--level` or `--generate`, or an open board. A
+/// level that fails to load or generate is reported and the open board is
+/// used instead.
+fn build_level(options: &Options) -> Level {
+    let mut level = if let Some(path) = &options.level {
+        load_level(path).unwrap_or_else(|err| {
+            eprintln!("Failed to load level {}.", path.display());
+            eprintln!("{err}");
+            Level::default()
+        })
+    } else if let Some(pattern) = options.generate {
+        let (rows, cols) = options.board.unwrap_or((DEFAULT_ROWS, DEFAULT_COLS));
+        // The drawn seed is kept with the level, so a saved level replays the
+        // same food as well as the same layout.
+        let seed = options.seed.unwrap_or_else(|| rand::random::<u32>() as u64);
+        let generator = Generator {
+            rows,
+            cols,
+            pattern,
+            density: options.density,
+            seed,
+        };
+        match generator.generate() {
+            Ok(level) => Level {
+                seed: Some(seed),
+                ..level
+            },
+            Err(err) => {
+                eprintln!("Failed to generate a {} level.", pattern.name());
+                eprintln!("{err}");
+                Level::default()
+            }
+        }
+    } else {
+        let (rows, cols) = options
+            .board
+            .unwrap_or((DEFAULT_BOARD_SIZE, DEFAULT_BOARD_SIZE));
+        Level {
+            rows,
+            cols,
+            ..Level::default()
+        }
+    };
+    if options.seed.is_some() {
+        level.seed = options.seed;
+    }
+    level
+}
+
+fn save_keymap(keymap: &Keymap) {
+    let path = default_keymap_path();
+    if let Err(err) = keymap.save(&path) {
+        eprintln!("Failed to save keymap to {}.", path.display());
+        eprintln!("{err}");
+    }
+}
+
+/// Loads the sprite sheet for `window`, or returns None to draw with shapes.
+/// Having no sheet at the default location is normal; a sheet the user asked
+/// for that fails to load is reported.
+fn load_sprites(window: &mut PistonWindow, path: Option<&Path>) -> Option<SpriteSheet> {
+    let (path, requested) = match path {
+        Some(path) => (path.to_path_buf(), true),
+        None => (default_sheet_path(), false),
+    };
+    if !requested && !path.exists() {
+        return None;
+    }
+    match SpriteSheet::load(window, &path) {
+        Ok(sheet) => Some(sheet),
+        Err(err) => {
+            eprintln!("Failed to load sprites from {}.", path.display());
+            eprintln!("{err}");
+            None
+        }
+    }
+}
+
+/// Starts a round on `level`. Snakes start along the top and bottom rows, so
+/// levels should keep those clear of obstacles.
+fn create_game(players: usize, settings: GameSettings, level: &Level) -> Game {
+    let num_rows = level.rows;
+    let num_cols = level.cols;
+    let length = settings.start_length.clamp(1, num_cols);
+    let mut snakes = vec![game::Snake::with_length(
+        game::Position::new(0, length as i32 - 1),
+        game::Direction::Right,
+        length,
+    )];
+    if players > 1 {
+        let last_row = num_rows as i32 - 1;
+        let last_col = num_cols as i32 - 1;
+        snakes.push(game::Snake::with_length(
+            game::Position::new(last_row, last_col - (length as i32 - 1)),
+            game::Direction::Left,
+            length,
+        ));
+    }
+    let food_position = game::Position::new(num_rows as i32 / 2, num_cols as i32 / 2);
+
+    let mut game = Game::new_multiplayer(num_rows, num_cols, snakes, food_position);
+    game.set_seed(level.seed.unwrap_or_else(rand::random));
+    game.set_settings(settings);
+    game.set_obstacles(level.obstacles.clone());
+    game.set_portals(level.portals.clone());
+    game
 }
 
 fn main() {
-    let mut game = create_game();
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Command::Play(options)) => *options,
+        Ok(Command::Help) => {
+            println!("{USAGE}");
+            return;
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            process::exit(2);
+        }
+    };
 
-    let mut window: PistonWindow = WindowSettings::new("Hello World!", [512; 2])
+    let daily = options.daily.then(Date::today);
+    let level = match daily {
+        Some(date) => daily_level(date),
+        None => build_level(&options),
+    };
+    if let Some(path) = &options.save_level {
+        if let Err(err) = save_level(&level, path) {
+            eprintln!("Failed to save level to {}.", path.display());
+            eprintln!("{err}");
+            process::exit(1);
+        }
+        println!("Saved level {} to {}", level.name, path.display());
+        return;
+    }
+    if let Some(address) = &options.server {
+        run_server(address, options.players, options.game, level);
+        return;
+    }
+    let mut themes = Themes::load();
+    if let Some(name) = &options.theme {
+        if !themes.select(name) {
+            eprintln!(
+                "Unknown theme: {name}. Using {} instead.",
+                themes.current().name
+            );
+        }
+    }
+
+    let keymap = load_keymap(&options);
+
+    if let Some(address) = &options.connect {
+        run_network_client(address, &options, themes, keymap);
+        return;
+    }
+
+    match options.frontend {
+        Frontend::Window => run_window(&options, &level, daily, themes, keymap),
+        Frontend::Tui => {
+            if let Err(err) = tui::run(&options, &level, daily, themes, keymap) {
+                eprintln!("Terminal frontend failed.");
+                eprintln!("{err}");
+                process::exit(1);
+            }
+        }
+    }
+}
+
+fn build_window(size: [f64; 2], fullscreen: bool) -> PistonWindow {
+    WindowSettings::new("Rust Snake", size)
+        .resizable(true)
+        .fullscreen(fullscreen)
         .build()
         .unwrap_or_else(|err| {
             eprintln!("Failed to build window.");
             eprintln!("{err}");
             process::exit(1);
-        });
+        })
+}
+
+/// Tracks fullscreen state so F11 can switch back and forth. Piston cannot
+/// change fullscreen on a live window, so toggling rebuilds it.
+struct Display {
+    fullscreen: bool,
+    windowed_size: [f64; 2],
+}
+
+impl Display {
+    fn new(windowed_size: [f64; 2], fullscreen: bool) -> Display {
+        Display {
+            fullscreen,
+            windowed_size,
+        }
+    }
+
+    fn build_window(&self) -> PistonWindow {
+        build_window(self.windowed_size, self.fullscreen)
+    }
+
+    /// Rebuilds `window` on F11. Returns true if the key was handled.
+    fn handle_event(&mut self, window: &mut PistonWindow, event: &Event) -> bool {
+        if let Event::Input(Input::Button(button_args), _) = event {
+            if let (Keyboard(Key::F11), ButtonState::Press) =
+                (button_args.button, button_args.state)
+            {
+                if !self.fullscreen {
+                    let size = window.size();
+                    self.windowed_size = [size.width, size.height];
+                }
+                self.fullscreen = !self.fullscreen;
+                *window = self.build_window();
+                return true;
+            }
+        }
+        false
+    }
+}
+
+fn run_server(address: &str, players: usize, settings: GameSettings, level: Level) {
+    let mut server = Server::bind(
+        address,
+        Duration::from_millis(game::TICK_MILLIS),
+        Duration::from_millis(GAME_OVER_SLEEP_MS as u64),
+        move || create_game(players, settings, &level),
+    )
+    .unwrap_or_else(|err| {
+        eprintln!("Failed to start server on {address}.");
+        eprintln!("{err}");
+        process::exit(1);
+    });
+    if let Ok(local_address) = server.local_addr() {
+        println!("Waiting for {players} player(s) on {local_address}");
+    }
+    if let Err(err) = server.run() {
+        eprintln!("Server failed.");
+        eprintln!("{err}");
+        process::exit(1);
+    }
+}
+
+fn run_network_client(address: &str, options: &Options, mut themes: Themes, keymap: Keymap) {
+    let sprite_path = options.sprites.as_deref();
+    let mut client = Client::connect(address, None).unwrap_or_else(|err| {
+        eprintln!("Failed to connect to {address}.");
+        eprintln!("{err}");
+        process::exit(1);
+    });
+    let mut token = None;
+    let mut connected = true;
+    let mut last_attempt = Instant::now();
+    let mut game: Option<Game> = None;
+
+    let mut display = Display::new(
+        ScreenLayout::window_size(
+            DEFAULT_BOARD_SIZE,
+            DEFAULT_BOARD_SIZE,
+            Grid::Square,
+            options.cell_size,
+        ),
+        options.fullscreen,
+    );
+    let mut window = display.build_window();
+    let mut sprites = load_sprites(&mut window, sprite_path);
+    let mut gamepads = Gamepads::new();
+    while let Some(event) = window.next() {
+        enforce_min_size(&mut window, &event);
+        if display.handle_event(&mut window, &event) {
+            sprites = load_sprites(&mut window, sprite_path);
+            continue;
+        }
+        if let Event::Input(Input::Close(_close_args), _) = event {
+            break;
+        }
+        // The server decides which snake this client steers, so every
+        // player's turn keys and every gamepad steer it.
+        let pad_action = gamepads.action();
+        match pressed_key(&event)
+            .and_then(|key| keymap.action(&key_name(key)))
+            .or(pad_action)
+        {
+            Some(Action::Turn { direction, .. }) => {
+                // Relative turns are taken from the last heading the server
+                // reported, as the client doesn't simulate the game itself.
+                let heading = client
+                    .get_player()
+                    .zip(game.as_ref())
+                    .and_then(|(player, game)| game.get_snakes().get(player))
+                    .map_or(direction, |snake| snake.get_direction());
+                let topology = game
+                    .as_ref()
+                    .map_or(Grid::Square, |game| game.get_settings().grid)
+                    .topology();
+                if let Some(direction) =
+                    steered_direction(topology, heading, direction, options.controls)
+                {
+                    if client.send_direction(direction).is_err() {
+                        connected = false;
+                    }
+                }
+            }
+            Some(Action::CycleTheme) => themes.cycle(),
+            Some(Action::Quit) => break,
+            _ => (),
+        }
+
+        if connected {
+            match client.poll() {
+                Ok(true) => {
+                    token = client.get_token();
+                    game = client.get_snapshot().map(Snapshot::to_game);
+                }
+                Ok(false) => (),
+                Err(err) => {
+                    eprintln!("Lost connection: {err}");
+                    connected = false;
+                }
+            }
+        } else if last_attempt.elapsed().as_millis() > RECONNECT_INTERVAL_MS {
+            last_attempt = Instant::now();
+            if let Ok(new_client) = Client::connect(address, token) {
+                client = new_client;
+                connected = true;
+            }
+        }
+
+        if let Some(game) = &game {
+            update_title(&mut window, game);
+            render_game(
+                event,
+                &mut window,
+                game,
+                client.get_round_over().is_some(),
+                themes.current(),
+                sprites.as_ref(),
+            );
+        }
+    }
+}
+
+fn run_window(
+    options: &Options,
+    level: &Level,
+    daily: Option<Date>,
+    mut themes: Themes,
+    mut keymap: Keymap,
+) {
+    let players = options.players;
+    let sprite_path = options.sprites.as_deref();
+    let mut audio = Audio::new(options.audio);
+    let save_path = daily.map_or_else(default_save_path, daily_save_path);
+    let saved_game = load_saved_game(&save_path);
+    let mut awaiting_continue = saved_game.is_some();
+    let mut game = saved_game.unwrap_or_else(|| create_game(players, options.game, level));
+
+    let mut display = Display::new(
+        ScreenLayout::window_size(
+            game.get_num_rows(),
+            game.get_num_cols(),
+            game.get_settings().grid,
+            options.cell_size,
+        ),
+        options.fullscreen,
+    );
+    let mut window = display.build_window();
+    let mut sprites = load_sprites(&mut window, sprite_path);
     let mut start_time = Instant::now();
     let mut game_over_time = Instant::now();
+    let mut settings: Option<SettingsScreen> = None;
+    let mut gamepads = Gamepads::new();
 
     let mut game_over = false;
+    let mut paused = false;
     while let Some(event) = window.next() {
-        if game_over {
-            if game_over_time.elapsed().as_millis() > GAME_OVER_SLEEP_MS {
-                game = create_game();
-                game_over = false;
+        if let Event::Input(Input::Close(_close_args), _) = event {
+            // The save hasn't been resumed yet, so it is still the latest run.
+            if !awaiting_continue {
+                autosave(&game, game_over, &save_path);
             }
-        } else {
-            let duration = start_time.elapsed();
+            break;
+        }
+        enforce_min_size(&mut window, &event);
+        if display.handle_event(&mut window, &event) {
+            sprites = load_sprites(&mut window, sprite_path);
+            continue;
+        }
 
-            match event {
-                Event::Input(Input::Close(_close_args), _) => break,
-                Event::Input(Input::Button(_button_args), _) => {
-                    handle_buttons(_button_args, &mut game)
+        // Gamepads share the keymap's actions; the settings screen is
+        // keyboard only.
+        let mut action = gamepads.action().filter(|_| settings.is_none());
+        if let Some(key) = pressed_key(&event) {
+            let name = key_name(key);
+            if let Some(screen) = &mut settings {
+                if !screen.handle_key(&name, &mut keymap) {
+                    settings = None;
+                    save_keymap(&keymap);
                 }
-                _ => (),
+            } else if awaiting_continue && key == Key::C {
+                awaiting_continue = false;
+            } else if awaiting_continue && key == Key::N {
+                awaiting_continue = false;
+                discard_save(&save_path);
+                game = create_game(players, options.game, level);
+            } else {
+                action = keymap.action(&name);
             }
-            if duration.as_millis() > GAME_UPDATE_SPEED {
-                if let Err(err) = game.update_game() {
-                    eprintln!("Game over cause: {err}");
-                    game_over = true;
-                    game_over_time = Instant::now();
+        }
+        match action {
+            Some(Action::Turn { player, direction })
+                if !awaiting_continue && !paused && !game_over =>
+            {
+                steer(&mut game, player, direction, options.controls);
+            }
+            Some(Action::Pause) => paused = !paused && !awaiting_continue,
+            Some(Action::Restart) => {
+                awaiting_continue = false;
+                paused = false;
+                game_over = false;
+                discard_save(&save_path);
+                game = create_game(players, options.game, level);
+            }
+            Some(Action::Quit) => {
+                if !awaiting_continue {
+                    autosave(&game, game_over, &save_path);
                 }
-                start_time = Instant::now();
+                break;
             }
+            Some(Action::CycleTheme) => themes.cycle(),
+            Some(Action::Mute) => audio.toggle_mute(),
+            Some(Action::Settings) => settings = Some(SettingsScreen::new()),
+            _ => (),
         }
 
-        render_game(event, &mut window, &game, game_over);
+        if awaiting_continue || paused || settings.is_some() {
+            start_time = Instant::now();
+        } else if game_over {
+            if game_over_time.elapsed().as_millis() > GAME_OVER_SLEEP_MS {
+                game = create_game(players, options.game, level);
+                game_over = false;
+            }
+        } else if start_time.elapsed().as_millis() > tick_millis(&game) {
+            let result = game.update_game();
+            audio.play_events(game.get_events());
+            if let Err(err) = result {
+                eprintln!("Game over cause: {err}");
+                if let Some(date) = daily {
+                    eprintln!("{}", record_daily(date, &game));
+                }
+                game_over = true;
+                game_over_time = Instant::now();
+                discard_save(&save_path);
+            }
+            start_time = Instant::now();
+        }
+
+        update_title(&mut window, &game);
+        render_game(
+            event.clone(),
+            &mut window,
+            &game,
+            game_over,
+            themes.current(),
+            sprites.as_ref(),
+        );
+        if let Some(screen) = &settings {
+            render_prompt(event, &mut window, &screen.lines(&keymap), themes.current());
+        } else if awaiting_continue {
+            render_prompt(event, &mut window, &CONTINUE_PROMPT, themes.current());
+        } else if paused {
+            render_prompt(event, &mut window, &PAUSED_PROMPT, themes.current());
+        }
+    }
+}
+
+/// The run saved at `save_path`, if there is one. A save that can't be read
+/// is reported and a new game is started instead; the file is left alone.
+fn load_saved_game(save_path: &Path) -> Option<Game> {
+    match load_game(save_path) {
+        Ok(game) => Some(game),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => {
+            eprintln!("Failed to load saved game from {}.", save_path.display());
+            eprintln!("{err}");
+            None
+        }
     }
 }
+
+/// Saves a run in progress when the window closes. Finished runs have
+/// nothing to continue, so their save is removed instead.
+fn autosave(game: &Game, game_over: bool, save_path: &Path) {
+    if game_over {
+        discard_save(save_path);
+    } else if let Err(err) = save_game(game, save_path) {
+        eprintln!("Failed to save game to {}.", save_path.display());
+        eprintln!("{err}");
+    }
+}
+
+/// Keeps the score of a finished daily challenge in the high-score file and
+/// describes how it compares with the best for the day.
+fn record_daily(date: Date, game: &Game) -> String {
+    let path = default_scores_path();
+    let mut scores = HighScores::load(&path).unwrap_or_else(|err| {
+        eprintln!("Failed to read high scores from {}.", path.display());
+        eprintln!("{err}");
+        HighScores::default()
+    });
+    let key = date.to_string();
+    let best = scores.record_daily(&key, game.get_score());
+    if let Err(err) = scores.save(&path) {
+        eprintln!("Failed to save high scores to {}.", path.display());
+        eprintln!("{err}");
+    }
+    format!("Daily {key}: {} (best {best})", game.get_score())
+}
+
+fn discard_save(save_path: &Path) {
+    if let Err(err) = delete_save(save_path) {
+        eprintln!("Failed to remove {}.", save_path.display());
+        eprintln!("{err}");
+    }
+}
+
+fn render_prompt<S: AsRef<str>>(
+    event: Event,
+    window: &mut PistonWindow,
+    lines: &[S],
+    theme: &Theme,
+) {
+    let window_width = window.size().width;
+    let window_height = window.size().height;
+    let scale = display_scale(window);
+    // Wide enough for about 20 characters and tall enough for every line.
+    let rows = (lines.len() + 2) as f64 * (font::GLYPH_HEIGHT + 3.0);
+    let pixel = snap_down((window_width / 80.0).min(window_height / rows), scale).max(1.0 / scale);
+    let line_height = (font::GLYPH_HEIGHT + 3.0) * pixel;
+    let top = (window_height - line_height * lines.len() as f64) / 2.0;
+
+    window.draw_2d(&event, |c, g, _| {
+        rectangle(
+            theme.prompt_overlay,
+            [0.0, 0.0, window_width, window_height],
+            c.transform,
+            g,
+        );
+        for (index, line) in lines.iter().enumerate() {
+            font::draw_text_centered(
+                line.as_ref(),
+                window_width / 2.0,
+                top + index as f64 * line_height,
+                pixel,
+                theme.prompt_text,
+                c.transform,
+                g,
+            );
+        }
+    });
+}
@@ -0,0 +1,94 @@
+//! Rule sets layered on top of the basic game. Each mode decides when a round
+//! ends and how points are earned; `Game::update_game` asks the mode in
+//! `GameSettings` after moving the snakes.
+
+use crate::game::FoodKind;
+use serde::{Deserialize, Serialize};
+
+/// Ticks a snake can go without eating in survival before it loses a segment.
+pub static HUNGER_TICKS: u32 = 25;
+/// Regular food normally stays until eaten; in survival it rots after this.
+pub static SURVIVAL_FOOD_TICKS: u32 = 40;
+/// Survival awards a point for every this many ticks a snake stays alive.
+pub static SURVIVAL_POINT_TICKS: u64 = 20;
+pub static TIME_ATTACK_SECONDS: u32 = 60;
+
+#[derive(PartialEq, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Play until the snake dies; points come from food.
+    #[default]
+    Classic,
+    /// Score as much as possible from food before the clock runs out.
+    TimeAttack { seconds: u32 },
+    /// Food rots and a snake that doesn't eat often enough shrinks, starving
+    /// once nothing is left but its head. Points come from food and from
+    /// staying alive.
+    Survival,
+}
+
+impl GameMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::TimeAttack { .. } => "Time Attack",
+            GameMode::Survival => "Survival",
+        }
+    }
+
+    /// How long a round may last, in milliseconds of game time.
+    pub fn time_limit_ms(&self) -> Option<u64> {
+        match self {
+            GameMode::TimeAttack { seconds } => Some(*seconds as u64 * 1000),
+            _ => None,
+        }
+    }
+
+    /// Ticks an item of `kind` stays on the board, or None if it stays until
+    /// eaten.
+    pub fn food_lifetime(&self, kind: FoodKind) -> Option<u32> {
+        match self {
+            GameMode::Survival => kind.lifetime().or(Some(SURVIVAL_FOOD_TICKS)),
+            _ => kind.lifetime(),
+        }
+    }
+
+    /// Ticks without food after which a snake shrinks, if it ever does.
+    pub fn hunger_ticks(&self) -> Option<u32> {
+        match self {
+            GameMode::Survival => Some(HUNGER_TICKS),
+            _ => None,
+        }
+    }
+
+    /// Points each living snake earns on tick `tick`, on top of food.
+    pub fn tick_points(&self, tick: u64) -> u32 {
+        match self {
+            GameMode::Survival if tick.is_multiple_of(SURVIVAL_POINT_TICKS) => 1,
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn survival_food_rots_and_time_attack_has_a_limit() {
+        assert_eq!(GameMode::Classic.food_lifetime(FoodKind::Normal), None);
+        assert_eq!(
+            GameMode::Survival.food_lifetime(FoodKind::Normal),
+            Some(SURVIVAL_FOOD_TICKS)
+        );
+        assert_eq!(
+            GameMode::Survival.food_lifetime(FoodKind::Golden),
+            FoodKind::Golden.lifetime()
+        );
+        assert_eq!(
+            GameMode::TimeAttack { seconds: 120 }.time_limit_ms(),
+            Some(120_000)
+        );
+        assert_eq!(GameMode::Survival.time_limit_ms(), None);
+    }
+}
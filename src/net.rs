@@ -0,0 +1,872 @@
+//! Networked multiplayer. The server owns the only real `Game` and advances it
+//! on a fixed tick; clients send tick-tagged direction inputs and mirror the
+//! state from the snapshots and deltas the server broadcasts. Messages are
+//! newline-delimited JSON over TCP.
+//!
+//! There is no prediction or rollback: clients draw only what the server
+//! sent, and an input that arrives a little late is applied on the next tick
+//! rather than rewinding the game to the tick it was meant for.
+
+use crate::game::{ActivePowerUp, Direction, Food, Game, GameSettings, Portal, Position, Snake};
+use crate::mode::GameMode;
+use crate::obstacle::Obstacle;
+use crate::topology::Grid;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Inputs tagged with a tick the server already simulated are still applied
+/// on the next tick, as long as they are at most this many ticks late.
+pub static LATE_INPUT_TOLERANCE_TICKS: u64 = 4;
+/// Inputs tagged this many ticks or more in the future are dropped.
+pub static MAX_INPUT_LEAD_TICKS: u64 = 16;
+/// A peer that leaves more than this many bytes unread is dropped.
+pub static MAX_UNSENT_BYTES: usize = 1 << 20;
+/// A client that sends a longer message than this is dropped. Messages from
+/// the server carry whole boards, so they may be up to `MAX_UNSENT_BYTES` long.
+pub static MAX_CLIENT_LINE_BYTES: usize = 64 << 10;
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum ClientMessage {
+    /// Joins the game, or takes back a previous seat when `token` is set.
+    Join {
+        token: Option<u64>,
+    },
+    Input {
+        tick: u64,
+        direction: Direction,
+    },
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum ServerMessage {
+    Welcome {
+        player: usize,
+        token: u64,
+        snapshot: Snapshot,
+    },
+    Snapshot(Snapshot),
+    Delta(Delta),
+    RoundOver {
+        cause: String,
+    },
+    Full,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct SnakeState {
+    pub body: VecDeque<Position>,
+    pub direction: Direction,
+    pub score: u32,
+    pub alive: bool,
+    pub power_ups: Vec<ActivePowerUp>,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub tick: u64,
+    pub num_rows: usize,
+    pub num_cols: usize,
+    pub snakes: Vec<SnakeState>,
+    pub food: Vec<Food>,
+    /// Cells covered by obstacles this tick.
+    pub obstacles: Vec<Position>,
+    /// Portals never change during a round, so deltas leave them out.
+    pub portals: Vec<Portal>,
+    /// Clients need the grid to draw the board and to turn relative to it.
+    pub grid: Grid,
+    /// Clients need the mode and the game time to show the clock.
+    pub mode: GameMode,
+    pub elapsed_ms: u64,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct SnakeDelta {
+    /// New head pushed this tick, if the snake moved.
+    pub head: Option<Position>,
+    /// Length after the tick. Tail segments beyond it are dropped.
+    pub length: usize,
+    pub direction: Direction,
+    pub score: u32,
+    pub alive: bool,
+    pub power_ups: Vec<ActivePowerUp>,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Delta {
+    pub tick: u64,
+    pub snakes: Vec<SnakeDelta>,
+    /// New food, if it changed.
+    pub food: Option<Vec<Food>>,
+    /// New obstacle cells, if any obstacle moved.
+    pub obstacles: Option<Vec<Position>>,
+    pub elapsed_ms: u64,
+}
+
+impl Snapshot {
+    pub fn from_game(game: &Game, tick: u64) -> Snapshot {
+        Snapshot {
+            tick,
+            num_rows: game.get_num_rows(),
+            num_cols: game.get_num_cols(),
+            snakes: game
+                .get_snakes()
+                .iter()
+                .map(|snake| SnakeState {
+                    body: snake.get_body().clone(),
+                    direction: snake.get_direction(),
+                    score: snake.get_score(),
+                    alive: snake.is_alive(),
+                    power_ups: snake.get_power_ups().to_vec(),
+                })
+                .collect(),
+            food: game.get_food().to_vec(),
+            obstacles: game.get_obstacle_cells(),
+            portals: game.get_portals().to_vec(),
+            grid: game.get_settings().grid,
+            mode: game.get_settings().mode,
+            elapsed_ms: game.get_elapsed_ms(),
+        }
+    }
+
+    /// Describes how to get from `self` to `next`.
+    pub fn delta_to(&self, next: &Snapshot) -> Delta {
+        Delta {
+            tick: next.tick,
+            snakes: self
+                .snakes
+                .iter()
+                .zip(&next.snakes)
+                .map(|(old, new)| SnakeDelta {
+                    head: (old.body.front() != new.body.front())
+                        .then(|| *new.body.front().unwrap()),
+                    length: new.body.len(),
+                    direction: new.direction,
+                    score: new.score,
+                    alive: new.alive,
+                    power_ups: new.power_ups.clone(),
+                })
+                .collect(),
+            food: (self.food != next.food).then(|| next.food.clone()),
+            obstacles: (self.obstacles != next.obstacles).then(|| next.obstacles.clone()),
+            elapsed_ms: next.elapsed_ms,
+        }
+    }
+
+    pub fn apply(&mut self, delta: &Delta) {
+        self.tick = delta.tick;
+        self.elapsed_ms = delta.elapsed_ms;
+        for (snake, snake_delta) in self.snakes.iter_mut().zip(&delta.snakes) {
+            if let Some(head) = snake_delta.head {
+                snake.body.push_front(head);
+            }
+            snake.body.truncate(snake_delta.length);
+            snake.direction = snake_delta.direction;
+            snake.score = snake_delta.score;
+            snake.alive = snake_delta.alive;
+            snake.power_ups = snake_delta.power_ups.clone();
+        }
+        if let Some(food) = &delta.food {
+            self.food = food.clone();
+        }
+        if let Some(obstacles) = &delta.obstacles {
+            self.obstacles = obstacles.clone();
+        }
+    }
+
+    /// Builds a `Game` mirroring this snapshot, for rendering on a client.
+    pub fn to_game(&self) -> Game {
+        let snakes = self
+            .snakes
+            .iter()
+            .map(|snake| {
+                Snake::restore(
+                    snake.body.clone(),
+                    snake.direction,
+                    snake.score,
+                    snake.alive,
+                    snake.power_ups.clone(),
+                )
+            })
+            .collect();
+        // Bodies split across a portal aren't contiguous, which
+        // `new_multiplayer` would reject, so the snakes are put in afterwards.
+        let mut game = Game::new_multiplayer(
+            self.num_rows,
+            self.num_cols,
+            vec![Snake::new(VecDeque::new(), Direction::Right)],
+            Position::new(0, 0),
+        );
+        game.set_settings(GameSettings {
+            grid: self.grid,
+            mode: self.mode,
+            ..GameSettings::default()
+        });
+        game.set_elapsed_ms(self.elapsed_ms);
+        game.set_snakes(snakes);
+        game.set_portals(self.portals.clone());
+        // Only where the obstacles are now is sent, so the mirror holds them
+        // as still blocks.
+        game.set_obstacles(
+            self.obstacles
+                .iter()
+                .map(|position| Obstacle::Block {
+                    position: *position,
+                })
+                .collect(),
+        );
+        game.set_food(self.food.clone());
+        game
+    }
+}
+
+/// A nonblocking TCP stream that reads and writes newline-delimited JSON.
+/// Whatever the peer is not ready to take yet waits in `unsent`.
+struct Connection {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    max_line: usize,
+    unsent: Vec<u8>,
+    closed: bool,
+}
+
+impl Connection {
+    fn new(stream: TcpStream, max_line: usize) -> io::Result<Connection> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Connection {
+            stream,
+            buffer: Vec::new(),
+            max_line,
+            unsent: Vec::new(),
+            closed: false,
+        })
+    }
+
+    fn send<T: Serialize>(&mut self, message: &T) -> io::Result<()> {
+        serde_json::to_writer(&mut self.unsent, message)?;
+        self.unsent.push(b'\n');
+        self.flush()
+    }
+
+    /// Writes as much of the unsent data as the peer takes without blocking.
+    /// Fails once the peer has fallen more than `MAX_UNSENT_BYTES` behind.
+    fn flush(&mut self) -> io::Result<()> {
+        let mut written = 0;
+        while written < self.unsent.len() {
+            match self.stream.write(&self.unsent[written..]) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(count) => written += count,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+        self.unsent.drain(..written);
+        if self.unsent.len() > MAX_UNSENT_BYTES {
+            return Err(io::Error::other("Peer is not reading"));
+        }
+        Ok(())
+    }
+
+    /// Returns every complete message received so far. Fails once the peer
+    /// has disconnected and everything it sent has been returned, or once it
+    /// has sent more than `max_line` bytes without ending the line.
+    fn receive<T: for<'de> Deserialize<'de>>(&mut self) -> io::Result<Vec<T>> {
+        let mut chunk = [0; 4096];
+        while !self.closed && self.buffer.len() <= self.max_line {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => self.closed = true,
+                Ok(count) => self.buffer.extend_from_slice(&chunk[..count]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        let mut messages = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            messages.push(serde_json::from_slice(&line)?);
+        }
+        if self.buffer.len() > self.max_line {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Message is too long",
+            ));
+        }
+        if messages.is_empty() && self.closed {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        Ok(messages)
+    }
+}
+
+struct Seat {
+    token: Option<u64>,
+    connection: Option<Connection>,
+}
+
+/// Authoritative game server. Waits until every seat has been taken, then
+/// ticks the game and broadcasts a delta after every tick. Players who drop
+/// keep their snake and can reconnect with the token they were given.
+pub struct Server {
+    listener: TcpListener,
+    new_game: Box<dyn FnMut() -> Game>,
+    game: Game,
+    tick: u64,
+    tick_interval: Duration,
+    round_over_pause: Duration,
+    last_tick: Instant,
+    round_over_at: Option<Instant>,
+    seats: Vec<Seat>,
+    pending: Vec<Connection>,
+    inputs: BTreeMap<u64, Vec<(usize, Direction)>>,
+}
+
+impl Server {
+    pub fn bind(
+        address: impl ToSocketAddrs,
+        tick_interval: Duration,
+        round_over_pause: Duration,
+        mut new_game: impl FnMut() -> Game + 'static,
+    ) -> io::Result<Server> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let game = new_game();
+        let seats = game
+            .get_snakes()
+            .iter()
+            .map(|_| Seat {
+                token: None,
+                connection: None,
+            })
+            .collect();
+        Ok(Server {
+            listener,
+            new_game: Box::new(new_game),
+            game,
+            tick: 0,
+            tick_interval,
+            round_over_pause,
+            last_tick: Instant::now(),
+            round_over_at: None,
+            seats,
+            pending: Vec::new(),
+            inputs: BTreeMap::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn get_game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn get_tick(&self) -> u64 {
+        self.tick
+    }
+
+    fn started(&self) -> bool {
+        self.seats.iter().all(|seat| seat.token.is_some())
+    }
+
+    /// Runs the server forever.
+    pub fn run(&mut self) -> io::Result<()> {
+        loop {
+            self.poll()?;
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Accepts connections, reads inputs, sends what is left of earlier
+    /// messages and advances the game if a tick is due.
+    pub fn poll(&mut self) -> io::Result<()> {
+        self.accept()?;
+        self.read_pending();
+        self.read_inputs();
+        self.flush();
+
+        if !self.started() {
+            return Ok(());
+        }
+        if let Some(round_over_at) = self.round_over_at {
+            if round_over_at.elapsed() >= self.round_over_pause {
+                self.start_round();
+            }
+        } else if self.last_tick.elapsed() >= self.tick_interval.div_f64(self.game.get_speed()) {
+            self.last_tick = Instant::now();
+            self.advance();
+        }
+        Ok(())
+    }
+
+    fn accept(&mut self) -> io::Result<()> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if let Ok(connection) = Connection::new(stream, MAX_CLIENT_LINE_BYTES) {
+                        self.pending.push(connection);
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Seats connections that have sent their `Join` message.
+    fn read_pending(&mut self) {
+        let mut still_pending = Vec::new();
+        for mut connection in self.pending.drain(..) {
+            let Ok(messages) = connection.receive::<ClientMessage>() else {
+                continue;
+            };
+            let join = messages.into_iter().find_map(|message| match message {
+                ClientMessage::Join { token } => Some(token),
+                _ => None,
+            });
+            let Some(token) = join else {
+                still_pending.push(connection);
+                continue;
+            };
+
+            let seat = match token {
+                Some(token) => self.seats.iter().position(|seat| seat.token == Some(token)),
+                None => self.seats.iter().position(|seat| seat.token.is_none()),
+            };
+            let Some(player) = seat else {
+                let _ = connection.send(&ServerMessage::Full);
+                continue;
+            };
+            let token = token.unwrap_or_else(rand::random);
+            let welcome = ServerMessage::Welcome {
+                player,
+                token,
+                snapshot: Snapshot::from_game(&self.game, self.tick),
+            };
+            if connection.send(&welcome).is_ok() {
+                self.seats[player].token = Some(token);
+                self.seats[player].connection = Some(connection);
+            }
+        }
+        self.pending = still_pending;
+    }
+
+    fn read_inputs(&mut self) {
+        let next_tick = self.tick + 1;
+        for (player, seat) in self.seats.iter_mut().enumerate() {
+            let Some(connection) = seat.connection.as_mut() else {
+                continue;
+            };
+            match connection.receive::<ClientMessage>() {
+                Ok(messages) => {
+                    for message in messages {
+                        if let ClientMessage::Input { tick, direction } = message {
+                            if tick + LATE_INPUT_TOLERANCE_TICKS < next_tick
+                                || tick >= next_tick + MAX_INPUT_LEAD_TICKS
+                            {
+                                continue;
+                            }
+                            self.inputs
+                                .entry(tick.max(next_tick))
+                                .or_default()
+                                .push((player, direction));
+                        }
+                    }
+                }
+                Err(_) => seat.connection = None,
+            }
+        }
+    }
+
+    fn advance(&mut self) {
+        let before = Snapshot::from_game(&self.game, self.tick);
+        self.tick += 1;
+        if let Some(inputs) = self.inputs.remove(&self.tick) {
+            for (player, direction) in inputs {
+                self.game.queue_direction(player, direction);
+            }
+        }
+        let result = self.game.update_game();
+        let after = Snapshot::from_game(&self.game, self.tick);
+        self.broadcast(&ServerMessage::Delta(before.delta_to(&after)));
+
+        if let Err(cause) = result {
+            self.broadcast(&ServerMessage::RoundOver {
+                cause: cause.to_string(),
+            });
+            self.round_over_at = Some(Instant::now());
+        }
+    }
+
+    fn start_round(&mut self) {
+        self.game = (self.new_game)();
+        self.inputs.clear();
+        self.round_over_at = None;
+        self.last_tick = Instant::now();
+        let snapshot = Snapshot::from_game(&self.game, self.tick);
+        self.broadcast(&ServerMessage::Snapshot(snapshot));
+    }
+
+    /// Drops players who have stopped reading what they are sent.
+    fn flush(&mut self) {
+        for seat in &mut self.seats {
+            if let Some(connection) = seat.connection.as_mut() {
+                if connection.flush().is_err() {
+                    seat.connection = None;
+                }
+            }
+        }
+    }
+
+    fn broadcast(&mut self, message: &ServerMessage) {
+        for seat in &mut self.seats {
+            if let Some(connection) = seat.connection.as_mut() {
+                if connection.send(message).is_err() {
+                    seat.connection = None;
+                }
+            }
+        }
+    }
+}
+
+pub struct Client {
+    connection: Connection,
+    player: Option<usize>,
+    token: Option<u64>,
+    snapshot: Option<Snapshot>,
+    round_over: Option<String>,
+}
+
+impl Client {
+    /// Connects and asks for a seat. Pass the token from an earlier session
+    /// to take back the same snake after a disconnect.
+    pub fn connect(address: impl ToSocketAddrs, token: Option<u64>) -> io::Result<Client> {
+        let mut connection = Connection::new(TcpStream::connect(address)?, MAX_UNSENT_BYTES)?;
+        connection.send(&ClientMessage::Join { token })?;
+        Ok(Client {
+            connection,
+            player: None,
+            token,
+            snapshot: None,
+            round_over: None,
+        })
+    }
+
+    pub fn get_player(&self) -> Option<usize> {
+        self.player
+    }
+
+    pub fn get_token(&self) -> Option<u64> {
+        self.token
+    }
+
+    pub fn get_snapshot(&self) -> Option<&Snapshot> {
+        self.snapshot.as_ref()
+    }
+
+    /// Cause of the last game over, until the next round starts.
+    pub fn get_round_over(&self) -> Option<&str> {
+        self.round_over.as_deref()
+    }
+
+    /// Sends turns the server was not ready for yet and applies everything
+    /// it sent since the last call. Returns true if the mirrored state changed.
+    pub fn poll(&mut self) -> io::Result<bool> {
+        self.connection.flush()?;
+        let messages = self.connection.receive::<ServerMessage>()?;
+        let changed = !messages.is_empty();
+        for message in messages {
+            match message {
+                ServerMessage::Welcome {
+                    player,
+                    token,
+                    snapshot,
+                } => {
+                    self.player = Some(player);
+                    self.token = Some(token);
+                    self.snapshot = Some(snapshot);
+                }
+                ServerMessage::Snapshot(snapshot) => {
+                    self.snapshot = Some(snapshot);
+                    self.round_over = None;
+                }
+                ServerMessage::Delta(delta) => {
+                    if let Some(snapshot) = self.snapshot.as_mut() {
+                        snapshot.apply(&delta);
+                    }
+                }
+                ServerMessage::RoundOver { cause } => self.round_over = Some(cause),
+                ServerMessage::Full => {
+                    return Err(io::Error::other("Server is full"));
+                }
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Sends a turn, tagged with the tick it should be applied on.
+    pub fn send_direction(&mut self, direction: Direction) -> io::Result<()> {
+        let tick = self.snapshot.as_ref().map_or(0, |snapshot| snapshot.tick) + 1;
+        self.connection
+            .send(&ClientMessage::Input { tick, direction })
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn create_server() -> Server {
+        Server::bind("127.0.0.1:0", Duration::ZERO, Duration::ZERO, || {
+            let snakes = vec![
+                Snake::new(
+                    [Position::new(0, 1), Position::new(0, 0)].into(),
+                    Direction::Right,
+                ),
+                Snake::new(
+                    [Position::new(9, 8), Position::new(9, 9)].into(),
+                    Direction::Left,
+                ),
+            ];
+            Game::new_multiplayer(10, 10, snakes, Position::new(5, 5))
+        })
+        .unwrap()
+    }
+
+    /// A turn that keeps the given player's starting snake away from the walls.
+    fn safe_turn(player: usize) -> Direction {
+        if player == 0 {
+            Direction::Down
+        } else {
+            Direction::Up
+        }
+    }
+
+    /// Polls server and clients until `done` holds and every client has a seat,
+    /// failing after a timeout.
+    fn pump(server: &mut Server, clients: &mut [&mut Client], done: impl Fn(&Server) -> bool) {
+        let start = Instant::now();
+        while !done(server) || clients.iter().any(|client| client.get_player().is_none()) {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            server.poll().unwrap();
+            for client in clients.iter_mut() {
+                client.poll().unwrap();
+            }
+        }
+    }
+
+    fn sync(server: &mut Server, clients: &mut [&mut Client]) {
+        let start = Instant::now();
+        loop {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            for client in clients.iter_mut() {
+                client.poll().unwrap();
+            }
+            let tick = server.get_tick();
+            if clients
+                .iter()
+                .all(|client| client.get_snapshot().map(|s| s.tick) == Some(tick))
+            {
+                return;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn snapshot_delta_round_trip() {
+        let mut game = Game::default();
+        game.set_obstacles(vec![Obstacle::Patrol {
+            waypoints: vec![Position::new(8, 0), Position::new(8, 5)],
+            period: 1,
+        }]);
+        let before = Snapshot::from_game(&game, 0);
+        game.update_game().unwrap();
+        let after = Snapshot::from_game(&game, 1);
+
+        let mut mirrored = before.clone();
+        let delta = before.delta_to(&after);
+        assert_eq!(delta.obstacles, Some(vec![Position::new(8, 1)]));
+        mirrored.apply(&delta);
+        assert_eq!(mirrored, after);
+        assert_eq!(mirrored.to_game().get_obstacle_cells(), after.obstacles);
+    }
+
+    #[test]
+    fn to_game_keeps_snakes_split_by_portals() {
+        let mut game = Game::default();
+        game.set_portals(vec![Portal {
+            a: Position::new(2, 3),
+            b: Position::new(7, 7),
+        }]);
+        game.update_game().unwrap();
+        game.update_game().unwrap();
+        let snapshot = Snapshot::from_game(&game, 2);
+        let mirrored = snapshot.to_game();
+        assert_eq!(mirrored.get_snake_positions(), game.get_snake_positions());
+        assert_eq!(mirrored.get_snake_positions()[0], Position::new(7, 8));
+        assert_eq!(mirrored.get_portals(), game.get_portals());
+    }
+
+    #[test]
+    fn to_game_keeps_the_grid_and_mode() {
+        let mut game = Game::default();
+        let mode = GameMode::TimeAttack { seconds: 30 };
+        game.set_settings(GameSettings {
+            grid: Grid::Hex,
+            mode,
+            ..GameSettings::default()
+        });
+        let before = Snapshot::from_game(&game, 0);
+        game.update_game().unwrap();
+        let mut mirrored = before.clone();
+        mirrored.apply(&before.delta_to(&Snapshot::from_game(&game, 1)));
+        let mirrored = mirrored.to_game();
+        assert_eq!(mirrored.get_settings().grid, Grid::Hex);
+        assert_eq!(mirrored.get_settings().mode, mode);
+        assert_eq!(mirrored.get_time_left_ms(), game.get_time_left_ms());
+        assert!(mirrored.get_time_left_ms() < Some(30_000));
+    }
+
+    #[test]
+    fn clients_mirror_server_state() {
+        let mut server = create_server();
+        let address = server.local_addr().unwrap();
+        let mut first = Client::connect(address, None).unwrap();
+        let mut second = Client::connect(address, None).unwrap();
+
+        pump(&mut server, &mut [&mut first, &mut second], |server| {
+            server.get_tick() >= 1
+        });
+        assert_ne!(first.get_player(), second.get_player());
+
+        let player = first.get_player().unwrap();
+        let turn = safe_turn(player);
+        first.send_direction(turn).unwrap();
+        pump(&mut server, &mut [&mut first, &mut second], |server| {
+            server.get_tick() >= 4
+        });
+        sync(&mut server, &mut [&mut first, &mut second]);
+
+        let expected = Snapshot::from_game(server.get_game(), server.get_tick());
+        assert_eq!(first.get_snapshot(), Some(&expected));
+        assert_eq!(second.get_snapshot(), Some(&expected));
+        assert_eq!(expected.snakes[player].direction, turn);
+    }
+
+    #[test]
+    fn client_reconnects_to_same_seat() {
+        let mut server = create_server();
+        let address = server.local_addr().unwrap();
+        let mut first = Client::connect(address, None).unwrap();
+        let mut second = Client::connect(address, None).unwrap();
+        pump(&mut server, &mut [&mut first, &mut second], |server| {
+            server.get_tick() >= 1
+        });
+
+        let player = first.get_player();
+        let token = first.get_token();
+        drop(first);
+        let mut third = Client::connect(address, None).unwrap();
+        let mut rejoined = Client::connect(address, token).unwrap();
+        let start = Instant::now();
+        while rejoined.get_player().is_none() {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            server.poll().unwrap();
+            rejoined.poll().unwrap();
+        }
+        assert_eq!(rejoined.get_player(), player);
+        assert!(rejoined.get_snapshot().is_some());
+
+        // Every seat is taken, so a stranger is turned away.
+        server.poll().unwrap();
+        let start = Instant::now();
+        loop {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            server.poll().unwrap();
+            if third.poll().is_err() {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn stalled_client_is_dropped() {
+        let mut server = create_server();
+        let address = server.local_addr().unwrap();
+        let mut first = Client::connect(address, None).unwrap();
+        let mut second = Client::connect(address, None).unwrap();
+        pump(&mut server, &mut [&mut first, &mut second], |server| {
+            server.get_tick() >= 1
+        });
+
+        // The second client stops reading; the server keeps ticking for the
+        // first and lets go of the second once it is too far behind.
+        let player = second.get_player().unwrap();
+        let start = Instant::now();
+        while server.seats[player].connection.is_some() {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            let snapshot = Snapshot::from_game(&server.game, server.tick);
+            server.broadcast(&ServerMessage::Snapshot(snapshot));
+            server.poll().unwrap();
+            first.poll().unwrap();
+        }
+        assert!(server.seats[1 - player].connection.is_some());
+    }
+
+    #[test]
+    fn endless_line_drops_the_connection() {
+        let mut server = create_server();
+        let mut stream = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        stream
+            .write_all(&vec![b'x'; MAX_CLIENT_LINE_BYTES + 1])
+            .unwrap();
+        stream.set_nonblocking(true).unwrap();
+        let start = Instant::now();
+        loop {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            server.poll().unwrap();
+            match stream.read(&mut [0; 1]) {
+                Err(err) if err.kind() == ErrorKind::WouldBlock => (),
+                // Closed, or reset for leaving the rest unread.
+                _ => break,
+            }
+        }
+        assert!(server.pending.is_empty());
+    }
+
+    #[test]
+    fn late_inputs_are_applied_on_next_tick() {
+        let mut server = create_server();
+        let address = server.local_addr().unwrap();
+        let mut first = Client::connect(address, None).unwrap();
+        let mut second = Client::connect(address, None).unwrap();
+        pump(&mut server, &mut [&mut first, &mut second], |server| {
+            server.get_tick() >= 2
+        });
+
+        let tick = server.get_tick() - 1;
+        let player = first.get_player().unwrap();
+        first
+            .connection
+            .send(&ClientMessage::Input {
+                tick,
+                direction: safe_turn(player),
+            })
+            .unwrap();
+        let target = server.get_tick() + 3;
+        pump(&mut server, &mut [&mut first, &mut second], |server| {
+            server.get_tick() >= target
+        });
+        assert_eq!(
+            server.get_game().get_snakes()[player].get_direction(),
+            safe_turn(player)
+        );
+    }
+}
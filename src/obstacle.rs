@@ -0,0 +1,162 @@
+//! Hazards that sit on or move across the board. Where an obstacle is depends
+//! only on how many ticks have passed, so games with obstacles stay
+//! deterministic and replay the same way.
+
+use crate::game::{Direction, Position};
+use serde::{Deserialize, Serialize};
+
+/// Clockwise order a rotating bar sweeps through.
+static ROTATION: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+fn default_period() -> u32 {
+    1
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Obstacle {
+    /// A single wall cell that never moves.
+    Block { position: Position },
+    /// Walks cell by cell through `waypoints` and back again, moving every
+    /// `period` ticks. Consecutive waypoints are joined by going along the
+    /// row first, then the column.
+    Patrol {
+        waypoints: Vec<Position>,
+        #[serde(default = "default_period")]
+        period: u32,
+    },
+    /// A bar of `length` cells sticking out of `pivot` (which it covers too),
+    /// turning a quarter clockwise every `period` ticks.
+    Rotating {
+        pivot: Position,
+        length: usize,
+        #[serde(default = "default_period")]
+        period: u32,
+    },
+}
+
+/// Every cell of the path through `waypoints`, starting at the first one.
+fn route(waypoints: &[Position]) -> Vec<Position> {
+    let mut cells: Vec<Position> = waypoints.first().copied().into_iter().collect();
+    for target in waypoints.iter().skip(1) {
+        let mut position = *cells.last().unwrap();
+        while position != *target {
+            let direction = if position.get_column() < target.get_column() {
+                Direction::Right
+            } else if position.get_column() > target.get_column() {
+                Direction::Left
+            } else if position.get_row() < target.get_row() {
+                Direction::Down
+            } else {
+                Direction::Up
+            };
+            position = position.step(direction);
+            cells.push(position);
+        }
+    }
+    cells
+}
+
+fn bar(pivot: Position, length: usize, direction: Direction) -> Vec<Position> {
+    let mut cells = vec![pivot];
+    for _ in 0..length {
+        cells.push(cells.last().unwrap().step(direction));
+    }
+    cells
+}
+
+impl Obstacle {
+    /// Cells covered after `tick` ticks. They may lie off the board, where
+    /// they are simply never hit.
+    pub fn cells(&self, tick: u64) -> Vec<Position> {
+        match self {
+            Obstacle::Block { position } => vec![*position],
+            Obstacle::Patrol { waypoints, period } => {
+                let cells = route(waypoints);
+                if cells.len() < 2 {
+                    return cells;
+                }
+                let steps = tick / (*period).max(1) as u64;
+                let cycle = 2 * (cells.len() as u64 - 1);
+                let index = (steps % cycle) as usize;
+                let index = if index < cells.len() {
+                    index
+                } else {
+                    cycle as usize - index
+                };
+                vec![cells[index]]
+            }
+            Obstacle::Rotating {
+                pivot,
+                length,
+                period,
+            } => {
+                let steps = tick / (*period).max(1) as u64;
+                bar(*pivot, *length, ROTATION[(steps % 4) as usize])
+            }
+        }
+    }
+
+    /// Every cell the obstacle ever covers.
+    pub fn reach(&self) -> Vec<Position> {
+        match self {
+            Obstacle::Block { position } => vec![*position],
+            Obstacle::Patrol { waypoints, .. } => route(waypoints),
+            Obstacle::Rotating { pivot, length, .. } => ROTATION
+                .iter()
+                .flat_map(|direction| bar(*pivot, *length, *direction))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn patrol_walks_there_and_back() {
+        let patrol = Obstacle::Patrol {
+            waypoints: vec![Position::new(0, 0), Position::new(0, 2)],
+            period: 2,
+        };
+        let columns: Vec<i32> = (0..10)
+            .map(|tick| patrol.cells(tick)[0].get_column())
+            .collect();
+        assert_eq!(columns, vec![0, 0, 1, 1, 2, 2, 1, 1, 0, 0]);
+        assert_eq!(patrol.reach().len(), 3);
+    }
+
+    #[test]
+    fn patrol_goes_along_row_then_column() {
+        let cells = route(&[Position::new(0, 0), Position::new(2, 1)]);
+        assert_eq!(
+            cells,
+            vec![
+                Position::new(0, 0),
+                Position::new(0, 1),
+                Position::new(1, 1),
+                Position::new(2, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn rotating_bar_turns_clockwise() {
+        let bar = Obstacle::Rotating {
+            pivot: Position::new(5, 5),
+            length: 2,
+            period: 1,
+        };
+        assert_eq!(bar.cells(0)[2], Position::new(3, 5));
+        assert_eq!(bar.cells(1)[2], Position::new(5, 7));
+        assert_eq!(bar.cells(6)[2], Position::new(7, 5));
+        assert_eq!(bar.reach().len(), 12);
+    }
+}
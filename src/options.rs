@@ -0,0 +1,507 @@
+use std::path::PathBuf;
+
+use crate::audio::AudioSettings;
+use crate::keymap::{Controls, PRESETS};
+use rust_snake::game::GameSettings;
+use rust_snake::generate::Pattern;
+use rust_snake::mode::{GameMode, TIME_ATTACK_SECONDS};
+use rust_snake::topology::Grid;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Frontend {
+    Window,
+    Tui,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Options {
+    pub frontend: Frontend,
+    pub players: usize,
+    /// Size of one board cell in the window, in pixels.
+    pub cell_size: f64,
+    pub fullscreen: bool,
+    /// Address to run a headless multiplayer server on.
+    pub server: Option<String>,
+    /// Address of a multiplayer server to join.
+    pub connect: Option<String>,
+    /// Name of the theme to start with.
+    pub theme: Option<String>,
+    /// Sprite sheet to draw with instead of the default one.
+    pub sprites: Option<PathBuf>,
+    pub audio: AudioSettings,
+    /// Keymap preset to use instead of the user's keymap file.
+    pub keys: Option<String>,
+    pub controls: Controls,
+    pub game: GameSettings,
+    /// Level file to play instead of the open board.
+    pub level: Option<PathBuf>,
+    /// Play today's daily challenge.
+    pub daily: bool,
+    /// Rows and columns of the board, for the open board or a generated level.
+    pub board: Option<(usize, usize)>,
+    /// Generate a level in this style instead of loading one.
+    pub generate: Option<Pattern>,
+    /// Fraction of a generated board to wall off.
+    pub density: f64,
+    /// Seed for the generated layout and the food, to play the same game again.
+    pub seed: Option<u64>,
+    /// Write the level to this file and exit instead of playing.
+    pub save_level: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            frontend: Frontend::Window,
+            players: 1,
+            cell_size: 48.0,
+            fullscreen: false,
+            server: None,
+            connect: None,
+            theme: None,
+            sprites: None,
+            audio: AudioSettings::default(),
+            keys: None,
+            controls: Controls::Absolute,
+            game: GameSettings::default(),
+            level: None,
+            daily: false,
+            board: None,
+            generate: None,
+            density: 0.2,
+            seed: None,
+            save_level: None,
+        }
+    }
+}
+
+/// What the command line asks for.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Command {
+    Play(Box<Options>),
+    /// Print the usage and exit.
+    Help,
+}
+
+pub static USAGE: &str =
+    "Usage: rust_snake [--frontend window|tui] [--players 1|2] [--cell-size PIXELS] \
+                          [--fullscreen] [--theme NAME] [--sprites PNG] \
+                          [--volume 0-100] [--mute] [--no-music] [--keys default|wasd|vi] \
+                          [--controls absolute|relative] [--food 1-20] \
+                          [--growth 0-10] [--start-length 1-10] [--max-length LENGTH] \
+                          [--level TOML] [--mode classic|time-attack|survival] \
+                          [--time-limit SECONDS] [--grid square|octile|hex] \
+                          [--daily] [--board ROWSxCOLS] \
+                          [--generate rooms|corridors|symmetric] [--density 0-40] \
+                          [--seed N] [--save-level TOML] \
+                          [--server ADDRESS | --connect ADDRESS]";
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    let mut time_limit = None;
+    let mut density_set = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frontend" => {
+                let value = args.next().ok_or("--frontend needs a value")?;
+                options.frontend = match value.as_str() {
+                    "window" => Frontend::Window,
+                    "tui" => Frontend::Tui,
+                    _ => return Err(format!("Unknown frontend: {value}")),
+                };
+            }
+            "--players" => {
+                let value = args.next().ok_or("--players needs a value")?;
+                options.players = match value.as_str() {
+                    "1" => 1,
+                    "2" => 2,
+                    _ => return Err(format!("Unsupported number of players: {value}")),
+                };
+            }
+            "--cell-size" => {
+                let value = args.next().ok_or("--cell-size needs a value")?;
+                options.cell_size = match value.parse::<f64>() {
+                    Ok(size) if (8.0..=256.0).contains(&size) => size,
+                    _ => return Err(format!("Cell size must be between 8 and 256: {value}")),
+                };
+            }
+            "--fullscreen" => options.fullscreen = true,
+            "--server" => {
+                options.server = Some(args.next().ok_or("--server needs an address")?);
+            }
+            "--connect" => {
+                options.connect = Some(args.next().ok_or("--connect needs an address")?);
+            }
+            "--theme" => {
+                options.theme = Some(args.next().ok_or("--theme needs a name")?);
+            }
+            "--sprites" => {
+                options.sprites = Some(PathBuf::from(args.next().ok_or("--sprites needs a path")?));
+            }
+            "--volume" => {
+                let value = args.next().ok_or("--volume needs a value")?;
+                options.audio.volume = match value.parse::<u8>() {
+                    Ok(volume) if volume <= 100 => volume as f32 / 100.0,
+                    _ => return Err(format!("Volume must be between 0 and 100: {value}")),
+                };
+            }
+            "--mute" => options.audio.muted = true,
+            "--no-music" => options.audio.music = false,
+            "--keys" => {
+                let value = args.next().ok_or("--keys needs a preset")?;
+                if !PRESETS.contains(&value.as_str()) {
+                    return Err(format!("Unknown keymap preset: {value}"));
+                }
+                options.keys = Some(value);
+            }
+            "--controls" => {
+                let value = args.next().ok_or("--controls needs a value")?;
+                options.controls = match value.as_str() {
+                    "absolute" => Controls::Absolute,
+                    "relative" => Controls::Relative,
+                    _ => return Err(format!("Unknown control scheme: {value}")),
+                };
+            }
+            "--food" => {
+                let value = args.next().ok_or("--food needs a value")?;
+                options.game.max_food = match value.parse::<usize>() {
+                    Ok(count) if (1..=20).contains(&count) => count,
+                    _ => return Err(format!("Food count must be between 1 and 20: {value}")),
+                };
+            }
+            "--growth" => {
+                let value = args.next().ok_or("--growth needs a value")?;
+                options.game.growth_per_food = match value.parse::<usize>() {
+                    Ok(growth) if growth <= 10 => growth,
+                    _ => return Err(format!("Growth must be between 0 and 10: {value}")),
+                };
+            }
+            "--start-length" => {
+                let value = args.next().ok_or("--start-length needs a value")?;
+                options.game.start_length = match value.parse::<usize>() {
+                    Ok(length) if (1..=10).contains(&length) => length,
+                    _ => return Err(format!("Start length must be between 1 and 10: {value}")),
+                };
+            }
+            "--max-length" => {
+                let value = args.next().ok_or("--max-length needs a value")?;
+                options.game.max_length = match value.parse::<usize>() {
+                    Ok(length) if length >= 1 => Some(length),
+                    _ => return Err(format!("Invalid maximum length: {value}")),
+                };
+            }
+            "--mode" => {
+                let value = args.next().ok_or("--mode needs a value")?;
+                let seconds = match options.game.mode {
+                    GameMode::TimeAttack { seconds } => seconds,
+                    _ => TIME_ATTACK_SECONDS,
+                };
+                options.game.mode = match value.as_str() {
+                    "classic" => GameMode::Classic,
+                    "time-attack" => GameMode::TimeAttack { seconds },
+                    "survival" => GameMode::Survival,
+                    _ => return Err(format!("Unknown game mode: {value}")),
+                };
+            }
+            "--time-limit" => {
+                let value = args.next().ok_or("--time-limit needs a value")?;
+                let seconds = match value.parse::<u32>() {
+                    Ok(seconds) if (10..=600).contains(&seconds) => seconds,
+                    _ => return Err(format!("Time limit must be between 10 and 600: {value}")),
+                };
+                time_limit = Some(seconds);
+            }
+            "--grid" => {
+                let value = args.next().ok_or("--grid needs a value")?;
+                options.game.grid = Grid::parse(&value).ok_or(format!("Unknown grid: {value}"))?;
+            }
+            "--daily" => options.daily = true,
+            "--board" => {
+                let value = args.next().ok_or("--board needs a size")?;
+                let size = value
+                    .split_once('x')
+                    .and_then(|(rows, cols)| Some((rows.parse().ok()?, cols.parse().ok()?)));
+                options.board = match size {
+                    Some((rows, cols)) if (5..=60).contains(&rows) && (5..=60).contains(&cols) => {
+                        Some((rows, cols))
+                    }
+                    _ => return Err(format!("Board must be ROWSxCOLS, each 5 to 60: {value}")),
+                };
+            }
+            "--generate" => {
+                let value = args.next().ok_or("--generate needs a pattern")?;
+                options.generate =
+                    Some(Pattern::parse(&value).ok_or(format!("Unknown level pattern: {value}"))?);
+            }
+            "--density" => {
+                let value = args.next().ok_or("--density needs a value")?;
+                options.density = match value.parse::<u8>() {
+                    Ok(percent) if percent <= 40 => percent as f64 / 100.0,
+                    _ => return Err(format!("Density must be between 0 and 40: {value}")),
+                };
+                density_set = true;
+            }
+            "--seed" => {
+                // Small enough to fit the integers of a TOML level file.
+                let value = args.next().ok_or("--seed needs a value")?;
+                options.seed = Some(
+                    value
+                        .parse::<u32>()
+                        .map_err(|_| format!("Invalid seed: {value}"))? as u64,
+                );
+            }
+            "--save-level" => {
+                options.save_level = Some(PathBuf::from(
+                    args.next().ok_or("--save-level needs a path")?,
+                ));
+            }
+            "--level" => {
+                options.level = Some(PathBuf::from(args.next().ok_or("--level needs a path")?));
+            }
+            "-h" | "--help" => return Ok(Command::Help),
+            _ => return Err(format!("Unknown argument: {arg}")),
+        }
+    }
+
+    if let Some(seconds) = time_limit {
+        match &mut options.game.mode {
+            GameMode::TimeAttack { seconds: limit } => *limit = seconds,
+            _ => return Err(String::from("--time-limit needs --mode time-attack")),
+        }
+    }
+    if density_set && options.generate.is_none() {
+        return Err(String::from("--density needs --generate"));
+    }
+    if options.level.is_some() && (options.generate.is_some() || options.board.is_some()) {
+        return Err(String::from(
+            "--level sets the board itself and can't be combined with --generate or --board",
+        ));
+    }
+    // Everyone has to play the daily challenge by the same rules.
+    let changes_rules = options.players != 1
+        || options.game != GameSettings::default()
+        || options.level.is_some()
+        || options.board.is_some()
+        || options.generate.is_some()
+        || options.seed.is_some()
+        || options.save_level.is_some()
+        || options.server.is_some()
+        || options.connect.is_some();
+    if options.daily && changes_rules {
+        return Err(String::from(
+            "--daily is a single-player game with fixed rules and takes no game options",
+        ));
+    }
+    if options.server.is_some() && options.connect.is_some() {
+        return Err(String::from("--server and --connect cannot be combined"));
+    }
+
+    Ok(Command::Play(Box::new(options)))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        match parse_args(args.iter().map(|arg| arg.to_string()))? {
+            Command::Play(options) => Ok(*options),
+            Command::Help => Err(String::from("asked for help")),
+        }
+    }
+
+    #[test]
+    fn parse_args_defaults_to_window() {
+        assert_eq!(parse(&[]).unwrap(), Options::default());
+    }
+
+    #[test]
+    fn parse_args_help() {
+        for flag in ["-h", "--help"] {
+            let args = ["--players", "2", flag].map(String::from);
+            assert_eq!(parse_args(args), Ok(Command::Help));
+        }
+    }
+
+    #[test]
+    fn parse_args_frontend_tui() {
+        assert_eq!(
+            parse(&["--frontend", "tui"]).unwrap().frontend,
+            Frontend::Tui
+        );
+    }
+
+    #[test]
+    fn parse_args_two_players() {
+        assert_eq!(parse(&["--players", "2"]).unwrap().players, 2);
+        assert!(parse(&["--players", "3"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_server_and_connect_are_exclusive() {
+        let options = parse(&["--server", "0.0.0.0:7878", "--players", "2"]).unwrap();
+        assert_eq!(options.server.as_deref(), Some("0.0.0.0:7878"));
+        assert!(parse(&["--server", "a:1", "--connect", "b:2"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_cell_size() {
+        assert_eq!(parse(&["--cell-size", "32"]).unwrap().cell_size, 32.0);
+        assert!(parse(&["--cell-size", "2"]).is_err());
+        assert!(parse(&["--cell-size", "big"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_fullscreen() {
+        assert!(!parse(&[]).unwrap().fullscreen);
+        assert!(parse(&["--fullscreen"]).unwrap().fullscreen);
+    }
+
+    #[test]
+    fn parse_args_theme() {
+        assert_eq!(
+            parse(&["--theme", "High Contrast"])
+                .unwrap()
+                .theme
+                .as_deref(),
+            Some("High Contrast")
+        );
+        assert!(parse(&["--theme"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_sprites() {
+        assert_eq!(
+            parse(&["--sprites", "tiles.png"]).unwrap().sprites,
+            Some(PathBuf::from("tiles.png"))
+        );
+        assert!(parse(&["--sprites"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_audio() {
+        let options = parse(&["--volume", "40", "--mute", "--no-music"]).unwrap();
+        assert_eq!(options.audio.volume, 0.4);
+        assert!(options.audio.muted);
+        assert!(!options.audio.music);
+        assert!(parse(&["--volume", "101"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_keys_preset() {
+        assert_eq!(
+            parse(&["--keys", "vi"]).unwrap().keys.as_deref(),
+            Some("vi")
+        );
+        assert!(parse(&["--keys", "emacs"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_rejects_unknown_frontend() {
+        assert!(parse(&["--frontend", "curses"]).is_err());
+        assert!(parse(&["--frontend"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_relative_controls() {
+        assert_eq!(
+            parse(&["--controls", "relative"]).unwrap().controls,
+            Controls::Relative
+        );
+        assert!(parse(&["--controls", "tank"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_food_count() {
+        assert_eq!(parse(&["--food", "5"]).unwrap().game.max_food, 5);
+        assert!(parse(&["--food", "0"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_growth_and_lengths() {
+        let options =
+            parse(&["--growth", "3", "--start-length", "5", "--max-length", "20"]).unwrap();
+        assert_eq!(options.game.growth_per_food, 3);
+        assert_eq!(options.game.start_length, 5);
+        assert_eq!(options.game.max_length, Some(20));
+        assert!(parse(&["--start-length", "11"]).is_err());
+        assert!(parse(&["--max-length", "0"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_level() {
+        assert_eq!(
+            parse(&["--level", "maze.toml"]).unwrap().level,
+            Some(PathBuf::from("maze.toml"))
+        );
+        assert!(parse(&["--level"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_mode_and_time_limit() {
+        assert_eq!(
+            parse(&["--mode", "survival"]).unwrap().game.mode,
+            GameMode::Survival
+        );
+        assert_eq!(
+            parse(&["--time-limit", "120", "--mode", "time-attack"])
+                .unwrap()
+                .game
+                .mode,
+            GameMode::TimeAttack { seconds: 120 }
+        );
+        assert_eq!(
+            parse(&["--mode", "time-attack"]).unwrap().game.mode,
+            GameMode::TimeAttack {
+                seconds: TIME_ATTACK_SECONDS
+            }
+        );
+        assert!(parse(&["--time-limit", "60"]).is_err());
+        assert!(parse(&["--mode", "zen"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_grid() {
+        assert_eq!(parse(&[]).unwrap().game.grid, Grid::Square);
+        assert_eq!(parse(&["--grid", "hex"]).unwrap().game.grid, Grid::Hex);
+        assert!(parse(&["--grid", "triangle"]).is_err());
+        assert!(parse(&["--daily", "--grid", "octile"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_daily_takes_no_game_options() {
+        assert!(parse(&["--daily", "--theme", "Classic"]).unwrap().daily);
+        assert!(parse(&["--daily", "--food", "3"]).is_err());
+        assert!(parse(&["--players", "2", "--daily"]).is_err());
+        assert!(parse(&["--daily", "--level", "maze.toml"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_generated_level() {
+        let options = parse(&[
+            "--generate",
+            "corridors",
+            "--board",
+            "20x30",
+            "--density",
+            "25",
+            "--seed",
+            "7",
+            "--save-level",
+            "maze.toml",
+        ])
+        .unwrap();
+        assert_eq!(options.generate, Some(Pattern::Corridors));
+        assert_eq!(options.board, Some((20, 30)));
+        assert_eq!(options.density, 0.25);
+        assert_eq!(options.seed, Some(7));
+        assert_eq!(options.save_level, Some(PathBuf::from("maze.toml")));
+        assert!(parse(&["--board", "20"]).is_err());
+        assert!(parse(&["--density", "10"]).is_err());
+        assert!(parse(&["--generate", "caves"]).is_err());
+        assert!(parse(&["--generate", "rooms", "--level", "a.toml"]).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use crate::daily::Date;
+use crate::game::Game;
+use crate::storage::{data_dir, write_atomically};
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+pub fn default_save_path() -> PathBuf {
+    data_dir().join("save.json")
+}
+
+/// Daily challenges are saved apart from regular games, one file per day, so
+/// neither can be continued as the other.
+pub fn daily_save_path(date: Date) -> PathBuf {
+    data_dir().join(format!("daily-{date}.json"))
+}
+
+/// Writes the complete game state, including pending input and the random
+/// number generator, so a restored game continues exactly where it stopped.
+pub fn save_game(game: &Game, path: &Path) -> io::Result<()> {
+    let contents = serde_json::to_vec(game)?;
+    write_atomically(path, &contents)
+}
+
+pub fn load_game(path: &Path) -> io::Result<Game> {
+    let contents = fs::read(path)?;
+    let game: Game = serde_json::from_slice(&contents)?;
+    if !game.is_consistent() {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "Save file is corrupt",
+        ));
+    }
+    Ok(game)
+}
+
+/// Removes the save, e.g. once the saved run has ended. A missing file is fine.
+pub fn delete_save(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::game::Direction;
+    use crate::storage::temporary_path;
+
+    #[test]
+    fn save_and_load_restores_game_exactly() {
+        let path = temporary_path("restore.json");
+        let mut game = Game::default();
+        game.set_seed(42);
+        game.set_next_direction(Direction::Down);
+        save_game(&game, &path).unwrap();
+
+        let mut restored = load_game(&path).unwrap();
+        assert_eq!(restored, game);
+
+        for _ in 0..5 {
+            assert_eq!(restored.update_game(), game.update_game());
+        }
+        assert_eq!(restored, game);
+        delete_save(&path).unwrap();
+    }
+
+    #[test]
+    fn load_rejects_corrupt_save() {
+        let path = temporary_path("corrupt.json");
+        fs::write(&path, "{\"num_rows\": 3}").unwrap();
+        assert!(load_game(&path).is_err());
+        delete_save(&path).unwrap();
+        assert!(load_game(&path).is_err());
+    }
+
+    /// Writes `game` with the given fields left out, the way saves from
+    /// before those fields existed look.
+    fn write_without(game: &Game, fields: &[&str], path: &Path) {
+        let mut value = serde_json::to_value(game).unwrap();
+        for field in fields {
+            value.as_object_mut().unwrap().remove(*field);
+        }
+        fs::write(path, serde_json::to_vec(&value).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn load_accepts_saves_from_before_speed_items() {
+        let path = temporary_path("before_speed.json");
+        let game = Game::default();
+        write_without(&game, &["speed", "speed_ticks_left"], &path);
+        let restored = load_game(&path).unwrap();
+        assert_eq!(restored.get_speed(), 1.0);
+        assert_eq!(restored, game);
+        delete_save(&path).unwrap();
+    }
+
+    #[test]
+    fn load_accepts_saves_with_a_single_food_item() {
+        let game = Game::default();
+        let food = *game.get_food().first().unwrap();
+        let mut value = serde_json::to_value(&game).unwrap();
+        let fields = value.as_object_mut().unwrap();
+        fields.remove("settings");
+        fields.insert(String::from("food"), serde_json::to_value(food).unwrap());
+        let path = temporary_path("single_food.json");
+        fs::write(&path, serde_json::to_vec(&value).unwrap()).unwrap();
+        assert_eq!(load_game(&path).unwrap(), game);
+
+        // Before food kinds only the position was saved.
+        let fields = value.as_object_mut().unwrap();
+        fields.remove("food");
+        fields.insert(
+            String::from("food_position"),
+            serde_json::to_value(food.get_position()).unwrap(),
+        );
+        fs::write(&path, serde_json::to_vec(&value).unwrap()).unwrap();
+        assert_eq!(load_game(&path).unwrap(), game);
+        delete_save(&path).unwrap();
+    }
+
+    #[test]
+    fn load_accepts_saves_from_before_newer_settings() {
+        let game = Game::default();
+        let mut value = serde_json::to_value(&game).unwrap();
+        let settings = value["settings"].as_object_mut().unwrap();
+        settings.remove("mode");
+        // Nor are the growth settings in saves older still.
+        settings.remove("growth_per_food");
+        settings.remove("start_length");
+        let path = temporary_path("before_modes.json");
+        fs::write(&path, serde_json::to_vec(&value).unwrap()).unwrap();
+        assert_eq!(load_game(&path).unwrap(), game);
+        delete_save(&path).unwrap();
+    }
+
+    #[test]
+    fn delete_missing_save_is_ok() {
+        assert!(delete_save(&temporary_path("missing.json")).is_ok());
+    }
+}
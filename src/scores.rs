@@ -0,0 +1,68 @@
+use crate::storage::{data_dir, write_atomically};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+pub fn default_scores_path() -> PathBuf {
+    data_dir().join("scores.json")
+}
+
+/// Best results kept on this machine.
+#[derive(PartialEq, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    /// Best daily challenge score, keyed by `YYYY-MM-DD`.
+    #[serde(default)]
+    pub daily: BTreeMap<String, u32>,
+}
+
+impl HighScores {
+    /// Reads the file at `path`. A missing file just means no scores yet.
+    pub fn load(path: &Path) -> io::Result<HighScores> {
+        match fs::read(path) {
+            Ok(contents) => Ok(serde_json::from_slice(&contents)?),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(HighScores::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        write_atomically(path, &serde_json::to_vec_pretty(self)?)
+    }
+
+    /// Keeps `score` for the daily challenge of `date` if it beats the best so
+    /// far, and returns the best.
+    pub fn record_daily(&mut self, date: &str, score: u32) -> u32 {
+        let best = self.daily.entry(date.to_string()).or_insert(score);
+        *best = (*best).max(score);
+        *best
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::storage::temporary_path;
+
+    #[test]
+    fn record_daily_keeps_best_per_date() {
+        let mut scores = HighScores::default();
+        assert_eq!(scores.record_daily("2026-10-18", 7), 7);
+        assert_eq!(scores.record_daily("2026-10-18", 3), 7);
+        assert_eq!(scores.record_daily("2026-10-19", 3), 3);
+        assert_eq!(scores.daily.len(), 2);
+    }
+
+    #[test]
+    fn scores_round_trip_through_file() {
+        let path = temporary_path("scores.json");
+        assert_eq!(HighScores::load(&path).unwrap(), HighScores::default());
+        let mut scores = HighScores::default();
+        scores.record_daily("2026-10-18", 12);
+        scores.save(&path).unwrap();
+        assert_eq!(HighScores::load(&path).unwrap(), scores);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,494 @@
+use piston_window::math::Matrix2d;
+use piston_window::{ellipse, line_from_to, polygon, rectangle, G2d};
+use std::collections::VecDeque;
+
+use rust_snake::game::{step_through, Direction, Portal, Position, Snake};
+use rust_snake::topology::{Grid, SquareGrid, Topology};
+
+use crate::layout::BoardLayout;
+
+static EYE_COLOR: [f32; 4] = [0.05, 0.05, 0.05, 1.0];
+/// How much lighter the head is than the body color, and how much darker the tail.
+static HEAD_LIGHTEN: f32 = 0.25;
+static TAIL_DARKEN: f32 = 0.35;
+/// Gap between a segment and its cell edge, as a fraction of the cell.
+static INSET: f64 = 0.12;
+/// Thickness of the bars joining segments on grids with diagonals, as a
+/// fraction of the cell.
+static LINK_WIDTH: f64 = 0.5;
+
+/// What a single body cell looks like. Each `Option<Direction>` points to the
+/// neighbouring segment, or is `None` when that neighbour is not adjacent on
+/// the board (for instance when the body is split across the board). A
+/// neighbour on the far side of a portal counts as adjacent, so the segment
+/// reaches into the portal.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Segment {
+    Head {
+        facing: Direction,
+        toward_body: Option<Direction>,
+    },
+    Body {
+        toward_head: Option<Direction>,
+        toward_tail: Option<Direction>,
+    },
+    Tail {
+        toward_body: Option<Direction>,
+    },
+}
+
+/// Direction of the step from `from` to `to` on `topology`, if they are
+/// neighbours either directly or through one of `portals`.
+pub fn direction_between(
+    from: Position,
+    to: Position,
+    topology: &dyn Topology,
+    portals: &[Portal],
+) -> Option<Direction> {
+    topology
+        .directions()
+        .iter()
+        .copied()
+        .find(|direction| step_through(topology, portals, from, *direction) == to)
+}
+
+pub fn segments(
+    body: &VecDeque<Position>,
+    facing: Direction,
+    topology: &dyn Topology,
+    portals: &[Portal],
+) -> Vec<Segment> {
+    let last = body.len().saturating_sub(1);
+    body.iter()
+        .enumerate()
+        .map(|(index, position)| {
+            let toward_head = index.checked_sub(1).and_then(|previous| {
+                direction_between(*position, body[previous], topology, portals)
+            });
+            let toward_tail = body
+                .get(index + 1)
+                .and_then(|next| direction_between(*position, *next, topology, portals));
+            if index == 0 {
+                Segment::Head {
+                    facing,
+                    toward_body: toward_tail,
+                }
+            } else if index == last {
+                Segment::Tail {
+                    toward_body: toward_head,
+                }
+            } else {
+                Segment::Body {
+                    toward_head,
+                    toward_tail,
+                }
+            }
+        })
+        .collect()
+}
+
+fn scale_color(color: [f32; 4], factor: f32) -> [f32; 4] {
+    [
+        (color[0] * factor).clamp(0.0, 1.0),
+        (color[1] * factor).clamp(0.0, 1.0),
+        (color[2] * factor).clamp(0.0, 1.0),
+        color[3],
+    ]
+}
+
+/// Color of segment `index` out of `length`, fading from a lighter head to
+/// a darker tail so longer snakes show their length at a glance.
+pub fn gradient(color: [f32; 4], index: usize, length: usize) -> [f32; 4] {
+    let t = if length > 1 {
+        index as f32 / (length - 1) as f32
+    } else {
+        0.0
+    };
+    let factor = (1.0 + HEAD_LIGHTEN) * (1.0 - t) + (1.0 - TAIL_DARKEN) * t;
+    scale_color(color, factor)
+}
+
+/// Row/column offsets for `direction` on a square grid, as `(dx, dy)` on screen.
+fn screen_offset(direction: Direction) -> (f64, f64) {
+    match direction {
+        Direction::Left => (-1.0, 0.0),
+        Direction::Up => (0.0, -1.0),
+        Direction::Right => (1.0, 0.0),
+        Direction::Down => (0.0, 1.0),
+        Direction::UpLeft => (-1.0, -1.0),
+        Direction::UpRight => (1.0, -1.0),
+        Direction::DownLeft => (-1.0, 1.0),
+        Direction::DownRight => (1.0, 1.0),
+    }
+}
+
+/// Start and length along one axis of the strip between a cell's inset core
+/// and its edge, on the side `offset` points to. With no offset along the
+/// axis the strip spans the core.
+fn edge_span(start: f64, size: f64, offset: f64) -> (f64, f64) {
+    let inset = size * INSET;
+    if offset < 0.0 {
+        (start, inset)
+    } else if offset > 0.0 {
+        (start + size - inset, inset)
+    } else {
+        (start + inset, size - 2.0 * inset)
+    }
+}
+
+/// Rectangle joining the inset core of a cell to its edge on `direction`'s side.
+fn bridge(cell: [f64; 4], direction: Direction) -> [f64; 4] {
+    let [x, y, size, _] = cell;
+    let (dx, dy) = screen_offset(direction);
+    let (x, width) = edge_span(x, size, dx);
+    let (y, height) = edge_span(y, size, dy);
+    [x, y, width, height]
+}
+
+/// Half of the inset core on `direction`'s side, used to square off the part
+/// of a segment that joins its neighbour.
+fn half_core(cell: [f64; 4], direction: Direction) -> [f64; 4] {
+    let [x, y, size, _] = cell;
+    let inset = size * INSET;
+    let half = size / 2.0 - inset;
+    let span = |start: f64, offset: f64| {
+        if offset < 0.0 {
+            (start + inset, half)
+        } else if offset > 0.0 {
+            (start + size / 2.0, half)
+        } else {
+            (start + inset, size - 2.0 * inset)
+        }
+    };
+    let (dx, dy) = screen_offset(direction);
+    let (x, width) = span(x, dx);
+    let (y, height) = span(y, dy);
+    [x, y, width, height]
+}
+
+fn core(cell: [f64; 4]) -> [f64; 4] {
+    let [x, y, size, _] = cell;
+    let inset = size * INSET;
+    [x + inset, y + inset, size - 2.0 * inset, size - 2.0 * inset]
+}
+
+fn draw_head(
+    cell: [f64; 4],
+    facing: Direction,
+    toward_body: Option<Direction>,
+    color: [f32; 4],
+    transform: Matrix2d,
+    g: &mut G2d,
+) {
+    ellipse(color, core(cell), transform, g);
+    if let Some(direction) = toward_body {
+        rectangle(color, half_core(cell, direction), transform, g);
+        rectangle(color, bridge(cell, direction), transform, g);
+    }
+
+    draw_eyes(cell, screen_offset(facing), transform, g);
+}
+
+/// Eyes looking along `forward`, a screen offset of any length.
+fn draw_eyes(cell: [f64; 4], forward: (f64, f64), transform: Matrix2d, g: &mut G2d) {
+    let size = cell[2];
+    let center = (cell[0] + size / 2.0, cell[1] + size / 2.0);
+    let length = forward.0.hypot(forward.1).max(f64::EPSILON);
+    let (forward_x, forward_y) = (forward.0 / length, forward.1 / length);
+    let (side_x, side_y) = (-forward_y, forward_x);
+    let eye_size = size * 0.18;
+    for side in [-1.0, 1.0] {
+        let eye_x = center.0 + forward_x * size * 0.18 + side_x * side * size * 0.2;
+        let eye_y = center.1 + forward_y * size * 0.18 + side_y * side * size * 0.2;
+        ellipse(
+            EYE_COLOR,
+            [
+                eye_x - eye_size / 2.0,
+                eye_y - eye_size / 2.0,
+                eye_size,
+                eye_size,
+            ],
+            transform,
+            g,
+        );
+    }
+}
+
+fn draw_body(
+    cell: [f64; 4],
+    toward_head: Option<Direction>,
+    toward_tail: Option<Direction>,
+    color: [f32; 4],
+    transform: Matrix2d,
+    g: &mut G2d,
+) {
+    let is_straight = match (toward_head, toward_tail) {
+        (Some(head), Some(tail)) => {
+            screen_offset(head) == {
+                let (x, y) = screen_offset(tail);
+                (-x, -y)
+            }
+        }
+        _ => false,
+    };
+    if is_straight {
+        rectangle(color, core(cell), transform, g);
+    } else {
+        // Turns and loose ends get a round core, which rounds the outer corner.
+        ellipse(color, core(cell), transform, g);
+    }
+    for direction in [toward_head, toward_tail].into_iter().flatten() {
+        rectangle(color, half_core(cell, direction), transform, g);
+        rectangle(color, bridge(cell, direction), transform, g);
+    }
+}
+
+fn draw_tail(
+    cell: [f64; 4],
+    toward_body: Option<Direction>,
+    color: [f32; 4],
+    transform: Matrix2d,
+    g: &mut G2d,
+) {
+    let Some(direction) = toward_body else {
+        ellipse(color, core(cell), transform, g);
+        return;
+    };
+    let [x, y, size, _] = cell;
+    let inset = size * INSET;
+    let (dx, dy) = screen_offset(direction);
+    let center = (x + size / 2.0, y + size / 2.0);
+    let half_width = size / 2.0 - inset;
+    // Wide where it joins the body, narrowing to a point near the far edge.
+    let base = (center.0 + dx * size / 2.0, center.1 + dy * size / 2.0);
+    let tip = (
+        center.0 - dx * (size / 2.0 - inset),
+        center.1 - dy * (size / 2.0 - inset),
+    );
+    let (side_x, side_y) = (-dy, dx);
+    polygon(
+        color,
+        &[
+            [base.0 + side_x * half_width, base.1 + side_y * half_width],
+            [base.0 - side_x * half_width, base.1 - side_y * half_width],
+            [tip.0, tip.1],
+        ],
+        transform,
+        g,
+    );
+}
+
+pub fn draw_snake(
+    snake: &Snake,
+    portals: &[Portal],
+    color: [f32; 4],
+    head_color: [f32; 4],
+    layout: &BoardLayout,
+    transform: Matrix2d,
+    g: &mut G2d,
+) {
+    if layout.grid != Grid::Square {
+        draw_linked_snake(snake, portals, color, head_color, layout, transform, g);
+        return;
+    }
+    let body = snake.get_body();
+    let length = body.len();
+    // Drawn tail first so the head ends up on top.
+    for (index, segment) in segments(body, snake.get_direction(), &SquareGrid, portals)
+        .into_iter()
+        .enumerate()
+        .rev()
+    {
+        let cell = layout.cell_rect(body[index]);
+        let color = gradient(color, index, length);
+        match segment {
+            Segment::Head {
+                facing,
+                toward_body,
+            } => draw_head(cell, facing, toward_body, head_color, transform, g),
+            Segment::Body {
+                toward_head,
+                toward_tail,
+            } => draw_body(cell, toward_head, toward_tail, color, transform, g),
+            Segment::Tail { toward_body } => draw_tail(cell, toward_body, color, transform, g),
+        }
+    }
+}
+
+fn center(cell: [f64; 4]) -> [f64; 2] {
+    [cell[0] + cell[2] / 2.0, cell[1] + cell[3] / 2.0]
+}
+
+/// Snakes on grids with diagonals are drawn as round segments joined by bars
+/// reaching halfway to their neighbours, since the square pieces only join
+/// edge to edge.
+fn draw_linked_snake(
+    snake: &Snake,
+    portals: &[Portal],
+    color: [f32; 4],
+    head_color: [f32; 4],
+    layout: &BoardLayout,
+    transform: Matrix2d,
+    g: &mut G2d,
+) {
+    let topology = layout.grid.topology();
+    let body = snake.get_body();
+    let length = body.len();
+    for (index, segment) in segments(body, snake.get_direction(), topology, portals)
+        .into_iter()
+        .enumerate()
+        .rev()
+    {
+        let position = body[index];
+        let cell = layout.cell_rect(position);
+        let from = center(cell);
+        // Screen offset to the neighbouring cell; through a portal that is
+        // the portal cell itself.
+        let toward = |direction: Direction| {
+            let to = center(layout.cell_rect(topology.step(position, direction)));
+            (to[0] - from[0], to[1] - from[1])
+        };
+        let (color, links) = match segment {
+            Segment::Head { toward_body, .. } => (head_color, [toward_body, None]),
+            Segment::Body {
+                toward_head,
+                toward_tail,
+            } => (gradient(color, index, length), [toward_head, toward_tail]),
+            Segment::Tail { toward_body } => (gradient(color, index, length), [toward_body, None]),
+        };
+        ellipse(color, core(cell), transform, g);
+        for direction in links.into_iter().flatten() {
+            let (dx, dy) = toward(direction);
+            let to = [from[0] + dx / 2.0, from[1] + dy / 2.0];
+            line_from_to(color, cell[2] * LINK_WIDTH / 2.0, from, to, transform, g);
+        }
+        if let Segment::Head { facing, .. } = segment {
+            draw_eyes(cell, toward(facing), transform, g);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rust_snake::topology::HexGrid;
+
+    fn body(cells: &[(i32, i32)]) -> VecDeque<Position> {
+        cells
+            .iter()
+            .map(|(row, column)| Position::new(*row, *column))
+            .collect()
+    }
+
+    #[test]
+    fn segments_classify_head_turn_and_tail() {
+        let body = body(&[(0, 2), (0, 1), (1, 1), (2, 1)]);
+        assert_eq!(
+            segments(&body, Direction::Right, &SquareGrid, &[]),
+            vec![
+                Segment::Head {
+                    facing: Direction::Right,
+                    toward_body: Some(Direction::Left),
+                },
+                Segment::Body {
+                    toward_head: Some(Direction::Right),
+                    toward_tail: Some(Direction::Down),
+                },
+                Segment::Body {
+                    toward_head: Some(Direction::Up),
+                    toward_tail: Some(Direction::Down),
+                },
+                Segment::Tail {
+                    toward_body: Some(Direction::Up),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn segments_single_cell_is_a_head() {
+        let body = body(&[(3, 3)]);
+        assert_eq!(
+            segments(&body, Direction::Up, &SquareGrid, &[]),
+            vec![Segment::Head {
+                facing: Direction::Up,
+                toward_body: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn segments_do_not_connect_distant_cells() {
+        let body = body(&[(0, 0), (0, 9)]);
+        assert_eq!(
+            segments(&body, Direction::Left, &SquareGrid, &[])[1],
+            Segment::Tail { toward_body: None }
+        );
+    }
+
+    #[test]
+    fn segments_connect_through_portals() {
+        let portals = [Portal {
+            a: Position::new(0, 3),
+            b: Position::new(5, 5),
+        }];
+        let body = body(&[(5, 6), (0, 2), (0, 1)]);
+        let segments = segments(&body, Direction::Right, &SquareGrid, &portals);
+        assert_eq!(
+            segments[0],
+            Segment::Head {
+                facing: Direction::Right,
+                toward_body: Some(Direction::Left),
+            }
+        );
+        assert_eq!(
+            segments[1],
+            Segment::Body {
+                toward_head: Some(Direction::Right),
+                toward_tail: Some(Direction::Left),
+            }
+        );
+    }
+
+    #[test]
+    fn segments_follow_hex_neighbours() {
+        // Straight down the board on a hex grid zigzags between diagonals.
+        let body = body(&[(2, 1), (1, 1), (0, 1)]);
+        assert_eq!(
+            segments(&body, Direction::DownLeft, &HexGrid, &[]),
+            vec![
+                Segment::Head {
+                    facing: Direction::DownLeft,
+                    toward_body: Some(Direction::UpRight),
+                },
+                Segment::Body {
+                    toward_head: Some(Direction::DownLeft),
+                    toward_tail: Some(Direction::UpLeft),
+                },
+                Segment::Tail {
+                    toward_body: Some(Direction::DownRight),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn bridges_reach_the_side_of_the_neighbour() {
+        let cell = [0.0, 0.0, 100.0, 100.0];
+        assert_eq!(bridge(cell, Direction::Left), [0.0, 12.0, 12.0, 76.0]);
+        assert_eq!(bridge(cell, Direction::DownRight), [88.0, 88.0, 12.0, 12.0]);
+        assert_eq!(half_core(cell, Direction::Up), [12.0, 12.0, 76.0, 38.0]);
+    }
+
+    #[test]
+    fn gradient_fades_from_head_to_tail() {
+        let color = [0.4, 0.4, 0.4, 1.0];
+        let head = gradient(color, 0, 5);
+        let middle = gradient(color, 2, 5);
+        let tail = gradient(color, 4, 5);
+        assert!(head[0] > middle[0] && middle[0] > tail[0]);
+        assert_eq!(head[3], 1.0);
+        assert_eq!(gradient(color, 0, 1), head);
+    }
+}
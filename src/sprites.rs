@@ -0,0 +1,260 @@
+use piston_window::math::Matrix2d;
+use piston_window::{
+    DrawState, Filter, Flip, G2d, G2dTexture, Image, ImageSize, PistonWindow, Texture,
+    TextureSettings,
+};
+use std::path::{Path, PathBuf};
+
+use rust_snake::game::{Direction, Food, Portal, Snake};
+use rust_snake::storage::data_dir;
+use rust_snake::topology::SquareGrid;
+
+use crate::layout::BoardLayout;
+use crate::snake_render::{segments, Segment};
+
+/// The sheet is a 4x4 grid of equally sized tiles:
+///
+/// | row | columns                                             |
+/// |-----|-----------------------------------------------------|
+/// | 0   | head facing up, right, down, left                   |
+/// | 1   | tail whose body is up, right, down, left            |
+/// | 2   | body horizontal, body vertical, food, golden food   |
+/// | 3   | corner up-right, right-down, down-left, left-up     |
+///
+/// Items without a tile of their own (shrink, speed boost, slow motion) use
+/// the golden food tile tinted with the theme's color for them.
+static SHEET_COLUMNS: u32 = 4;
+static SHEET_ROWS: u32 = 4;
+static WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Tile {
+    Head(Direction),
+    /// Tail whose body continues in the given direction.
+    Tail(Direction),
+    Horizontal,
+    Vertical,
+    /// Corner joining the two given neighbours.
+    Corner(Direction, Direction),
+    Food(usize),
+}
+
+/// Sheets only have tiles for square grids; diagonals get the vertical tile
+/// nearest to them.
+fn direction_column(direction: Direction) -> u32 {
+    match direction {
+        Direction::Up | Direction::UpLeft | Direction::UpRight => 0,
+        Direction::Right => 1,
+        Direction::Down | Direction::DownLeft | Direction::DownRight => 2,
+        Direction::Left => 3,
+    }
+}
+
+fn is_horizontal(direction: Direction) -> bool {
+    matches!(direction, Direction::Left | Direction::Right)
+}
+
+/// Which tile draws `segment`. Segments cut off from a neighbour (for
+/// instance by a portal) use a straight piece along the side that is joined.
+pub fn tile_for(segment: Segment) -> Tile {
+    match segment {
+        Segment::Head { facing, .. } => Tile::Head(facing),
+        Segment::Tail {
+            toward_body: Some(direction),
+        } => Tile::Tail(direction),
+        Segment::Tail { toward_body: None } => Tile::Horizontal,
+        Segment::Body {
+            toward_head: Some(head),
+            toward_tail: Some(tail),
+        } if is_horizontal(head) != is_horizontal(tail) => Tile::Corner(head, tail),
+        Segment::Body {
+            toward_head,
+            toward_tail,
+        } => match toward_head.or(toward_tail) {
+            Some(direction) if !is_horizontal(direction) => Tile::Vertical,
+            _ => Tile::Horizontal,
+        },
+    }
+}
+
+/// Column and row of `tile` in the sheet.
+pub fn tile_cell(tile: Tile) -> (u32, u32) {
+    match tile {
+        Tile::Head(direction) => (direction_column(direction), 0),
+        Tile::Tail(direction) => (direction_column(direction), 1),
+        Tile::Horizontal => (0, 2),
+        Tile::Vertical => (1, 2),
+        Tile::Food(variant) => (2 + variant.min(1) as u32, 2),
+        Tile::Corner(first, second) => {
+            let column = match (first, second) {
+                (Direction::Up, Direction::Right) | (Direction::Right, Direction::Up) => 0,
+                (Direction::Right, Direction::Down) | (Direction::Down, Direction::Right) => 1,
+                (Direction::Down, Direction::Left) | (Direction::Left, Direction::Down) => 2,
+                _ => 3,
+            };
+            (column, 3)
+        }
+    }
+}
+
+/// Default place to look for a sprite sheet.
+pub fn default_sheet_path() -> PathBuf {
+    data_dir().join("sprites.png")
+}
+
+pub struct SpriteSheet {
+    texture: G2dTexture,
+    tile_width: f64,
+    tile_height: f64,
+}
+
+impl SpriteSheet {
+    /// Loads the sheet for `window`. Textures belong to the window's graphics
+    /// context, so the sheet has to be loaded again whenever the window is
+    /// rebuilt.
+    pub fn load(window: &mut PistonWindow, path: &Path) -> Result<SpriteSheet, String> {
+        let mut context = window.create_texture_context();
+        let texture = Texture::from_path(
+            &mut context,
+            path,
+            Flip::None,
+            &TextureSettings::new().filter(Filter::Nearest),
+        )
+        .map_err(|err| err.to_string())?;
+        let (width, height) = texture.get_size();
+        if width < SHEET_COLUMNS || height < SHEET_ROWS {
+            return Err(format!("Sprite sheet is too small: {width}x{height}"));
+        }
+        Ok(SpriteSheet {
+            texture,
+            tile_width: (width / SHEET_COLUMNS) as f64,
+            tile_height: (height / SHEET_ROWS) as f64,
+        })
+    }
+
+    pub fn draw_tile(
+        &self,
+        tile: Tile,
+        rect: [f64; 4],
+        tint: [f32; 4],
+        transform: Matrix2d,
+        g: &mut G2d,
+    ) {
+        let (column, row) = tile_cell(tile);
+        Image::new_color(tint)
+            .src_rect([
+                column as f64 * self.tile_width,
+                row as f64 * self.tile_height,
+                self.tile_width,
+                self.tile_height,
+            ])
+            .rect(rect)
+            .draw(&self.texture, &DrawState::default(), transform, g);
+    }
+
+    /// Draws `food`; `color` is the theme's color for it, which tints items
+    /// that have no tile of their own and carries the fading of expiring ones.
+    pub fn draw_food(
+        &self,
+        food: &Food,
+        color: [f32; 4],
+        rect: [f64; 4],
+        transform: Matrix2d,
+        g: &mut G2d,
+    ) {
+        let variant = food.get_kind() as usize;
+        let tint = if variant <= 1 {
+            [1.0, 1.0, 1.0, color[3]]
+        } else {
+            color
+        };
+        self.draw_tile(Tile::Food(variant), rect, tint, transform, g);
+    }
+
+    /// Draws `snake` from tiles, in the sheet's own colors unless a `tint` is
+    /// given to tell players apart. Tiles only fit square grids.
+    pub fn draw_snake(
+        &self,
+        snake: &Snake,
+        portals: &[Portal],
+        tint: Option<[f32; 4]>,
+        layout: &BoardLayout,
+        transform: Matrix2d,
+        g: &mut G2d,
+    ) {
+        let tint = tint.unwrap_or(WHITE);
+        let body = snake.get_body();
+        for (position, segment) in body
+            .iter()
+            .zip(segments(body, snake.get_direction(), &SquareGrid, portals))
+            .rev()
+        {
+            self.draw_tile(
+                tile_for(segment),
+                layout.cell_rect(*position),
+                tint,
+                transform,
+                g,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn tile_for_picks_straight_and_corner_pieces() {
+        assert_eq!(
+            tile_for(Segment::Body {
+                toward_head: Some(Direction::Left),
+                toward_tail: Some(Direction::Right),
+            }),
+            Tile::Horizontal
+        );
+        assert_eq!(
+            tile_for(Segment::Body {
+                toward_head: Some(Direction::Up),
+                toward_tail: None,
+            }),
+            Tile::Vertical
+        );
+        assert_eq!(
+            tile_for(Segment::Body {
+                toward_head: Some(Direction::Down),
+                toward_tail: Some(Direction::Left),
+            }),
+            Tile::Corner(Direction::Down, Direction::Left)
+        );
+    }
+
+    #[test]
+    fn corners_map_to_the_same_tile_in_either_order() {
+        assert_eq!(
+            tile_cell(Tile::Corner(Direction::Left, Direction::Down)),
+            tile_cell(Tile::Corner(Direction::Down, Direction::Left))
+        );
+        assert_eq!(
+            tile_cell(Tile::Corner(Direction::Up, Direction::Left)),
+            (3, 3)
+        );
+        assert_eq!(
+            tile_cell(Tile::Corner(Direction::Right, Direction::Up)),
+            (0, 3)
+        );
+    }
+
+    #[test]
+    fn heads_and_tails_follow_their_direction() {
+        assert_eq!(tile_cell(Tile::Head(Direction::Left)), (3, 0));
+        assert_eq!(
+            tile_cell(tile_for(Segment::Tail {
+                toward_body: Some(Direction::Down)
+            })),
+            (2, 1)
+        );
+        assert_eq!(tile_cell(Tile::Food(5)), (3, 2));
+    }
+}
@@ -0,0 +1,34 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Directory for saves and other per-user files: `$XDG_DATA_HOME/rust_snake`,
+/// falling back to `~/.local/share/rust_snake` and then the working directory.
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(dir).join("rust_snake");
+    }
+    if let Some(home) = env::var_os("HOME").filter(|home| !home.is_empty()) {
+        return PathBuf::from(home).join(".local/share/rust_snake");
+    }
+    PathBuf::from(".")
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it into
+/// place, so a crash mid-write never leaves a truncated file behind.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, contents)?;
+    fs::rename(&temporary, path)
+}
+
+/// A path in the system temporary directory that no other test run uses.
+#[cfg(test)]
+pub fn temporary_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("rust_snake_{}_{name}", std::process::id()))
+}
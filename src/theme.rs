@@ -0,0 +1,339 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rust_snake::game::Food;
+use rust_snake::storage::data_dir;
+
+pub type Color = [f32; 4];
+
+/// Every color the frontends draw with. Themes are plain TOML files; any field
+/// a file leaves out keeps its value from the classic theme, e.g.
+///
+/// ```toml
+/// name = "Night"
+/// background = [0.05, 0.05, 0.1, 1.0]
+/// snakes = [[0.3, 0.8, 0.4, 1.0], [0.4, 0.6, 1.0, 1.0]]
+/// ```
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub name: String,
+    /// Body color for each player, repeating if there are more snakes.
+    pub snakes: Vec<Color>,
+    /// Head color for each player, repeating like `snakes`.
+    pub heads: Vec<Color>,
+    /// Food colors in `FoodKind` order: regular, golden, shrink, speed boost,
+    /// slow motion, ghost, shield and magnet. Missing entries repeat from the
+    /// start.
+    pub food: Vec<Color>,
+    pub walls: Color,
+    /// Color of each portal pair, repeating if there are more pairs.
+    pub portals: Vec<Color>,
+    /// Lines between cells. Fully transparent lines are not drawn.
+    pub grid: Color,
+    pub background: Color,
+    pub letterbox: Color,
+    pub hud: Color,
+    pub hud_text: Color,
+    pub game_over_overlay: Color,
+    pub prompt_overlay: Color,
+    pub prompt_text: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::classic()
+    }
+}
+
+fn pick(colors: &[Color], index: usize, fallback: Color) -> Color {
+    if colors.is_empty() {
+        fallback
+    } else {
+        colors[index % colors.len()]
+    }
+}
+
+impl Theme {
+    pub fn classic() -> Theme {
+        Theme {
+            name: String::from("Classic"),
+            snakes: vec![[0.2, 0.6, 0.3, 1.0], [0.2, 0.4, 0.7, 1.0]],
+            heads: vec![[0.25, 0.75, 0.38, 1.0], [0.25, 0.5, 0.88, 1.0]],
+            food: vec![
+                [0.7, 0.3, 0.2, 1.0],
+                [0.9, 0.75, 0.2, 1.0],
+                [0.55, 0.3, 0.7, 1.0],
+                [0.2, 0.75, 0.9, 1.0],
+                [0.85, 0.85, 0.95, 1.0],
+                [0.75, 0.75, 0.75, 0.6],
+                [0.3, 0.45, 0.95, 1.0],
+                [0.85, 0.15, 0.45, 1.0],
+            ],
+            walls: [0.2, 0.2, 0.2, 1.0],
+            portals: vec![[0.95, 0.55, 0.1, 1.0], [0.6, 0.3, 0.9, 1.0]],
+            grid: [0.0, 0.0, 0.0, 0.0],
+            background: [0.5, 0.5, 0.5, 1.0],
+            letterbox: [0.15, 0.15, 0.15, 1.0],
+            hud: [0.25, 0.25, 0.25, 1.0],
+            hud_text: [0.9, 0.9, 0.9, 1.0],
+            game_over_overlay: [0.8, 0.1, 0.1, 0.2],
+            prompt_overlay: [0.0, 0.0, 0.0, 0.6],
+            prompt_text: [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+
+    /// Saturated colors on black with a visible grid, for low-vision players.
+    pub fn high_contrast() -> Theme {
+        Theme {
+            name: String::from("High Contrast"),
+            snakes: vec![[1.0, 1.0, 1.0, 1.0], [1.0, 1.0, 0.0, 1.0]],
+            heads: vec![[0.0, 1.0, 1.0, 1.0], [1.0, 0.5, 0.0, 1.0]],
+            food: vec![
+                [1.0, 0.0, 1.0, 1.0],
+                [0.0, 1.0, 0.0, 1.0],
+                [1.0, 0.2, 0.2, 1.0],
+                [0.0, 0.6, 1.0, 1.0],
+                [0.6, 0.4, 1.0, 1.0],
+                [0.5, 0.5, 0.5, 1.0],
+                [0.0, 0.0, 1.0, 1.0],
+                [1.0, 0.6, 0.0, 1.0],
+            ],
+            walls: [0.6, 0.6, 0.6, 1.0],
+            portals: vec![[1.0, 0.5, 1.0, 1.0], [0.0, 1.0, 1.0, 1.0]],
+            grid: [0.25, 0.25, 0.25, 1.0],
+            background: [0.0, 0.0, 0.0, 1.0],
+            letterbox: [0.1, 0.1, 0.1, 1.0],
+            hud: [0.0, 0.0, 0.0, 1.0],
+            hud_text: [1.0, 1.0, 1.0, 1.0],
+            game_over_overlay: [1.0, 0.0, 0.0, 0.35],
+            prompt_overlay: [0.0, 0.0, 0.0, 0.85],
+            prompt_text: [1.0, 1.0, 0.0, 1.0],
+        }
+    }
+
+    /// Okabe-Ito palette, which stays distinguishable with the common forms
+    /// of color blindness.
+    pub fn colorblind_safe() -> Theme {
+        Theme {
+            name: String::from("Colorblind Safe"),
+            snakes: vec![[0.0, 0.45, 0.7, 1.0], [0.9, 0.62, 0.0, 1.0]],
+            heads: vec![[0.34, 0.71, 0.91, 1.0], [0.94, 0.89, 0.26, 1.0]],
+            food: vec![
+                [0.84, 0.37, 0.0, 1.0],
+                [0.8, 0.47, 0.65, 1.0],
+                [0.35, 0.35, 0.35, 1.0],
+                [0.0, 0.62, 0.45, 1.0],
+                [0.05, 0.05, 0.05, 1.0],
+                [0.6, 0.6, 0.6, 0.7],
+                [0.0, 0.45, 0.7, 1.0],
+                [0.8, 0.47, 0.65, 0.7],
+            ],
+            walls: [0.15, 0.15, 0.15, 1.0],
+            portals: vec![[0.8, 0.47, 0.65, 1.0], [0.34, 0.71, 0.91, 1.0]],
+            grid: [0.0, 0.0, 0.0, 0.15],
+            background: [0.85, 0.85, 0.85, 1.0],
+            letterbox: [0.15, 0.15, 0.15, 1.0],
+            hud: [0.25, 0.25, 0.25, 1.0],
+            hud_text: [1.0, 1.0, 1.0, 1.0],
+            game_over_overlay: [0.0, 0.0, 0.0, 0.3],
+            prompt_overlay: [0.0, 0.0, 0.0, 0.6],
+            prompt_text: [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+
+    pub fn built_in() -> Vec<Theme> {
+        vec![
+            Theme::classic(),
+            Theme::high_contrast(),
+            Theme::colorblind_safe(),
+        ]
+    }
+
+    pub fn snake_color(&self, index: usize) -> Color {
+        pick(&self.snakes, index, Theme::classic().snakes[0])
+    }
+
+    pub fn head_color(&self, index: usize) -> Color {
+        pick(&self.heads, index, self.snake_color(index))
+    }
+
+    pub fn food_color(&self, variant: usize) -> Color {
+        pick(&self.food, variant, Theme::classic().food[0])
+    }
+
+    pub fn portal_color(&self, index: usize) -> Color {
+        pick(&self.portals, index, Theme::classic().portals[0])
+    }
+
+    /// Color for an item on the board; items about to expire are faded.
+    pub fn item_color(&self, food: &Food) -> Color {
+        let mut color = self.food_color(food.get_kind() as usize);
+        if food.is_expiring() {
+            color[3] *= 0.5;
+        }
+        color
+    }
+}
+
+pub fn parse_theme(contents: &str) -> Result<Theme, String> {
+    toml::from_str(contents).map_err(|err| err.to_string())
+}
+
+/// Directory users drop their own `*.toml` themes into.
+pub fn themes_dir() -> PathBuf {
+    data_dir().join("themes")
+}
+
+/// Reads every `*.toml` file in `dir`, sorted by file name. Files that fail to
+/// parse are reported and skipped so one bad theme doesn't hide the others.
+pub fn load_themes(dir: &Path) -> io::Result<Vec<Theme>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "toml")
+        })
+        .collect();
+    paths.sort();
+
+    let mut themes = Vec::new();
+    for path in paths {
+        match fs::read_to_string(&path).map_err(|err| err.to_string()) {
+            Ok(contents) => match parse_theme(&contents) {
+                Ok(theme) => themes.push(theme),
+                Err(err) => {
+                    eprintln!("Ignoring theme {}.", path.display());
+                    eprintln!("{err}");
+                }
+            },
+            Err(err) => {
+                eprintln!("Failed to read theme {}.", path.display());
+                eprintln!("{err}");
+            }
+        }
+    }
+    Ok(themes)
+}
+
+/// The themes available at runtime and the one in use.
+pub struct Themes {
+    themes: Vec<Theme>,
+    current: usize,
+}
+
+impl Themes {
+    pub fn new(themes: Vec<Theme>) -> Themes {
+        let themes = if themes.is_empty() {
+            vec![Theme::classic()]
+        } else {
+            themes
+        };
+        Themes { themes, current: 0 }
+    }
+
+    /// Built-in themes followed by the user's themes. A missing themes
+    /// directory is normal and not reported.
+    pub fn load() -> Themes {
+        let mut themes = Theme::built_in();
+        let dir = themes_dir();
+        match load_themes(&dir) {
+            Ok(user_themes) => themes.extend(user_themes),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+            Err(err) => {
+                eprintln!("Failed to read themes from {}.", dir.display());
+                eprintln!("{err}");
+            }
+        }
+        Themes::new(themes)
+    }
+
+    pub fn current(&self) -> &Theme {
+        &self.themes[self.current]
+    }
+
+    pub fn cycle(&mut self) {
+        self.current = (self.current + 1) % self.themes.len();
+    }
+
+    /// Switches to the theme called `name`, ignoring case. Returns false if
+    /// there is no such theme.
+    pub fn select(&mut self, name: &str) -> bool {
+        match self
+            .themes
+            .iter()
+            .position(|theme| theme.name.eq_ignore_ascii_case(name))
+        {
+            Some(index) => {
+                self.current = index;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn built_in_themes_round_trip_through_toml() {
+        for theme in Theme::built_in() {
+            let contents = toml::to_string(&theme).unwrap();
+            assert_eq!(parse_theme(&contents).unwrap(), theme);
+        }
+    }
+
+    #[test]
+    fn missing_fields_fall_back_to_classic() {
+        let theme = parse_theme("name = \"Dark\"\nbackground = [0.0, 0.0, 0.0, 1.0]").unwrap();
+        assert_eq!(theme.name, "Dark");
+        assert_eq!(theme.background, [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(theme.food, Theme::classic().food);
+        assert!(parse_theme("background = \"black\"").is_err());
+    }
+
+    #[test]
+    fn colors_repeat_per_player_and_fall_back_when_empty() {
+        let theme = Theme {
+            heads: Vec::new(),
+            ..Theme::classic()
+        };
+        assert_eq!(theme.snake_color(2), theme.snakes[0]);
+        assert_eq!(theme.head_color(1), theme.snakes[1]);
+    }
+
+    #[test]
+    fn load_themes_skips_invalid_files() {
+        let dir = env::temp_dir().join(format!("rust_snake_{}_themes", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("b.toml"), "name = \"Second\"").unwrap();
+        fs::write(dir.join("a.toml"), "name = \"First\"").unwrap();
+        fs::write(dir.join("broken.toml"), "name = ").unwrap();
+        fs::write(dir.join("notes.txt"), "name = \"Ignored\"").unwrap();
+
+        let names: Vec<String> = load_themes(&dir)
+            .unwrap()
+            .into_iter()
+            .map(|theme| theme.name)
+            .collect();
+        assert_eq!(names, vec!["First", "Second"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cycle_wraps_and_select_ignores_case() {
+        let mut themes = Themes::new(Theme::built_in());
+        assert!(themes.select("high contrast"));
+        assert_eq!(themes.current().name, "High Contrast");
+        themes.cycle();
+        themes.cycle();
+        assert_eq!(themes.current().name, "Classic");
+        assert!(!themes.select("missing"));
+    }
+}
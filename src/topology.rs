@@ -0,0 +1,293 @@
+//! How cells connect. The board is always stored as rows and columns; a
+//! topology decides which directions a snake may move in and where a step
+//! leads, so the same board plays as squares, as squares with diagonal moves
+//! or as hexagons.
+
+use crate::game::{Direction, Position, Turn};
+use serde::{Deserialize, Serialize};
+
+pub trait Topology {
+    /// Directions a snake may move in, clockwise.
+    fn directions(&self) -> &'static [Direction];
+
+    /// The cell one step from `position` in `direction`.
+    fn step(&self, position: Position, direction: Direction) -> Position {
+        position.step(direction)
+    }
+
+    /// Fewest steps from `from` to `to` on an open board.
+    fn distance(&self, from: Position, to: Position) -> i32 {
+        (from.get_row() - to.get_row()).abs() + (from.get_column() - to.get_column()).abs()
+    }
+
+    /// `heading` rotated to the next allowed direction on the `turn` side.
+    fn turn(&self, heading: Direction, turn: Turn) -> Direction {
+        let directions = self.directions();
+        let Some(index) = directions.iter().position(|allowed| *allowed == heading) else {
+            return heading;
+        };
+        let count = directions.len();
+        match turn {
+            Turn::Left => directions[(index + count - 1) % count],
+            Turn::Right => directions[(index + 1) % count],
+        }
+    }
+
+    /// The heading a snake heading `heading` takes when its player presses
+    /// the key for `pressed`, or None if the key does nothing here. Keys only
+    /// come in four directions, so grids with others map them onto their own.
+    fn resolve(&self, _heading: Direction, pressed: Direction) -> Option<Direction> {
+        self.directions().contains(&pressed).then_some(pressed)
+    }
+}
+
+/// The classic grid: four directions, one cell at a time.
+pub struct SquareGrid;
+
+/// Squares with diagonal moves as well.
+pub struct OctileGrid;
+
+/// Hexagons, stored as rows with every odd row shifted half a cell to the
+/// right. There is no straight up or down; each row touches the two cells
+/// above and below it on either side.
+pub struct HexGrid;
+
+static SQUARE_DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+static OCTILE_DIRECTIONS: [Direction; 8] = [
+    Direction::Up,
+    Direction::UpRight,
+    Direction::Right,
+    Direction::DownRight,
+    Direction::Down,
+    Direction::DownLeft,
+    Direction::Left,
+    Direction::UpLeft,
+];
+
+static HEX_DIRECTIONS: [Direction; 6] = [
+    Direction::UpRight,
+    Direction::Right,
+    Direction::DownRight,
+    Direction::DownLeft,
+    Direction::Left,
+    Direction::UpLeft,
+];
+
+impl Topology for SquareGrid {
+    fn directions(&self) -> &'static [Direction] {
+        &SQUARE_DIRECTIONS
+    }
+}
+
+impl Topology for OctileGrid {
+    fn directions(&self) -> &'static [Direction] {
+        &OCTILE_DIRECTIONS
+    }
+
+    fn distance(&self, from: Position, to: Position) -> i32 {
+        let rows = (from.get_row() - to.get_row()).abs();
+        let columns = (from.get_column() - to.get_column()).abs();
+        rows.max(columns)
+    }
+
+    /// A key for a diagonal is taken as is. Any other key turns the snake
+    /// 45 degrees toward it, so going diagonally is a matter of pressing the
+    /// key once and going straight again of pressing it twice.
+    fn resolve(&self, heading: Direction, pressed: Direction) -> Option<Direction> {
+        if pressed.is_diagonal() {
+            return Some(pressed);
+        }
+        let index = |direction| OCTILE_DIRECTIONS.iter().position(|d| *d == direction);
+        let (Some(from), Some(to)) = (index(heading), index(pressed)) else {
+            return Some(pressed);
+        };
+        match (to + 8 - from) % 8 {
+            0 | 4 => Some(pressed),
+            1..=3 => Some(self.turn(heading, Turn::Right)),
+            _ => Some(self.turn(heading, Turn::Left)),
+        }
+    }
+}
+
+impl Topology for HexGrid {
+    fn directions(&self) -> &'static [Direction] {
+        &HEX_DIRECTIONS
+    }
+
+    fn step(&self, position: Position, direction: Direction) -> Position {
+        let row = position.get_row();
+        let column = position.get_column();
+        let shift = row.rem_euclid(2);
+        match direction {
+            Direction::UpLeft => Position::new(row - 1, column - 1 + shift),
+            Direction::UpRight => Position::new(row - 1, column + shift),
+            Direction::DownLeft => Position::new(row + 1, column - 1 + shift),
+            Direction::DownRight => Position::new(row + 1, column + shift),
+            _ => position.step(direction),
+        }
+    }
+
+    /// Counted on cube coordinates, where every step changes two of the
+    /// three axes by one.
+    fn distance(&self, from: Position, to: Position) -> i32 {
+        let cube = |position: Position| {
+            let row = position.get_row();
+            let x = position.get_column() - (row - row.rem_euclid(2)) / 2;
+            (x, row, -x - row)
+        };
+        let (x1, y1, z1) = cube(from);
+        let (x2, y2, z2) = cube(to);
+        ((x1 - x2).abs() + (y1 - y2).abs() + (z1 - z2).abs()) / 2
+    }
+
+    /// Up and down keys pick the diagonal on the side the snake is already
+    /// heading toward.
+    fn resolve(&self, heading: Direction, pressed: Direction) -> Option<Direction> {
+        let leftward = matches!(
+            heading,
+            Direction::Left | Direction::UpLeft | Direction::DownLeft
+        );
+        match (pressed, leftward) {
+            (Direction::Up, true) => Some(Direction::UpLeft),
+            (Direction::Up, false) => Some(Direction::UpRight),
+            (Direction::Down, true) => Some(Direction::DownLeft),
+            (Direction::Down, false) => Some(Direction::DownRight),
+            (pressed, _) => Some(pressed),
+        }
+    }
+}
+
+/// Which topology a game is played on.
+#[derive(PartialEq, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum Grid {
+    #[default]
+    Square,
+    Octile,
+    Hex,
+}
+
+impl Grid {
+    pub fn parse(name: &str) -> Option<Grid> {
+        match name {
+            "square" => Some(Grid::Square),
+            "octile" => Some(Grid::Octile),
+            "hex" => Some(Grid::Hex),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Grid::Square => "square",
+            Grid::Octile => "octile",
+            Grid::Hex => "hex",
+        }
+    }
+
+    pub fn topology(&self) -> &'static dyn Topology {
+        match self {
+            Grid::Square => &SquareGrid,
+            Grid::Octile => &OctileGrid,
+            Grid::Hex => &HexGrid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn hex_steps_depend_on_row_parity() {
+        let hex = Grid::Hex.topology();
+        let even = Position::new(2, 3);
+        let odd = Position::new(3, 3);
+        assert_eq!(hex.step(even, Direction::UpLeft), Position::new(1, 2));
+        assert_eq!(hex.step(even, Direction::DownRight), Position::new(3, 3));
+        assert_eq!(hex.step(odd, Direction::UpLeft), Position::new(2, 3));
+        assert_eq!(hex.step(odd, Direction::DownRight), Position::new(4, 4));
+        assert_eq!(hex.step(odd, Direction::Left), Position::new(3, 2));
+        // Every step can be walked back.
+        for position in [even, odd] {
+            for direction in hex.directions() {
+                let next = hex.step(position, *direction);
+                assert_eq!(hex.step(next, direction.opposite()), position);
+            }
+        }
+    }
+
+    #[test]
+    fn distance_counts_steps_on_each_grid() {
+        let from = Position::new(2, 2);
+        let to = Position::new(5, 4);
+        assert_eq!(Grid::Square.topology().distance(from, to), 5);
+        assert_eq!(Grid::Octile.topology().distance(from, to), 3);
+        assert_eq!(Grid::Hex.topology().distance(from, to), 4);
+        // Every neighbour of a hex cell is one step away.
+        let hex = Grid::Hex.topology();
+        for position in [from, Position::new(3, 3)] {
+            for direction in hex.directions() {
+                assert_eq!(hex.distance(position, hex.step(position, *direction)), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn turns_go_round_the_allowed_directions() {
+        assert_eq!(
+            Grid::Square.topology().turn(Direction::Up, Turn::Right),
+            Direction::Right
+        );
+        assert_eq!(
+            Grid::Octile.topology().turn(Direction::Up, Turn::Left),
+            Direction::UpLeft
+        );
+        assert_eq!(
+            Grid::Hex.topology().turn(Direction::UpRight, Turn::Left),
+            Direction::UpLeft
+        );
+    }
+
+    #[test]
+    fn keys_adapt_to_the_grid() {
+        assert_eq!(
+            Grid::Square
+                .topology()
+                .resolve(Direction::Right, Direction::UpLeft),
+            None
+        );
+        let octile = Grid::Octile.topology();
+        assert_eq!(
+            octile.resolve(Direction::Right, Direction::Up),
+            Some(Direction::UpRight)
+        );
+        assert_eq!(
+            octile.resolve(Direction::UpRight, Direction::Up),
+            Some(Direction::Up)
+        );
+        assert_eq!(
+            octile.resolve(Direction::DownLeft, Direction::Up),
+            Some(Direction::Left)
+        );
+        let hex = Grid::Hex.topology();
+        assert_eq!(
+            hex.resolve(Direction::Left, Direction::Up),
+            Some(Direction::UpLeft)
+        );
+        assert_eq!(
+            hex.resolve(Direction::DownRight, Direction::Up),
+            Some(Direction::UpRight)
+        );
+        assert_eq!(
+            hex.resolve(Direction::Right, Direction::Left),
+            Some(Direction::Left)
+        );
+    }
+}
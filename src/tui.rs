@@ -0,0 +1,370 @@
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor};
+use crossterm::{cursor, execute, queue, terminal};
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use rust_snake::daily::Date;
+use rust_snake::game::{Game, Position};
+use rust_snake::level::Level;
+
+use rust_snake::save::{daily_save_path, default_save_path};
+use rust_snake::topology::Grid;
+
+use crate::audio::Audio;
+use crate::keymap::{steer, Action, Keymap, SettingsScreen};
+use crate::options::Options;
+use crate::theme::{Theme, Themes};
+use crate::{
+    autosave, create_game, discard_save, hud_text, load_saved_game, record_daily, tick_millis,
+    GAME_OVER_SLEEP_MS,
+};
+
+static CONTINUE_PROMPT: &str = "Saved game found: c to continue, n for a new game";
+static PAUSED_STATUS: &str = "Paused";
+
+/// Puts the terminal into raw mode on an alternate screen and restores it on drop,
+/// so a panic or early return never leaves the user's shell unusable.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn new(out: &mut impl Write) -> io::Result<TerminalGuard> {
+        terminal::enable_raw_mode()?;
+        execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(
+            io::stdout(),
+            ResetColor,
+            cursor::Show,
+            terminal::LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn to_terminal_color(color: [f32; 4]) -> Color {
+    Color::Rgb {
+        r: (color[0] * 255.0) as u8,
+        g: (color[1] * 255.0) as u8,
+        b: (color[2] * 255.0) as u8,
+    }
+}
+
+/// Blends `overlay` on top of `base` using the overlay's alpha channel.
+fn blend(base: [f32; 4], overlay: [f32; 4]) -> [f32; 4] {
+    let alpha = overlay[3];
+    [
+        base[0] * (1.0 - alpha) + overlay[0] * alpha,
+        base[1] * (1.0 - alpha) + overlay[1] * alpha,
+        base[2] * (1.0 - alpha) + overlay[2] * alpha,
+        1.0,
+    ]
+}
+
+/// Name of `key` as used in keymap files, matching piston's key names so the
+/// same keymap works in both frontends.
+fn key_name(key: KeyCode) -> Option<String> {
+    let name = match key {
+        KeyCode::Char(' ') => String::from("Space"),
+        KeyCode::Char(c) if c.is_ascii_digit() => format!("D{c}"),
+        KeyCode::Char(c) => c.to_uppercase().to_string(),
+        KeyCode::Left => String::from("Left"),
+        KeyCode::Up => String::from("Up"),
+        KeyCode::Right => String::from("Right"),
+        KeyCode::Down => String::from("Down"),
+        KeyCode::Enter => String::from("Return"),
+        KeyCode::Esc => String::from("Escape"),
+        KeyCode::Tab => String::from("Tab"),
+        KeyCode::Backspace => String::from("Backspace"),
+        KeyCode::F(number) => format!("F{number}"),
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Ctrl-C always quits, whatever the keymap says.
+fn is_interrupt(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+fn controls_help(keymap: &Keymap) -> String {
+    let keys = |action| keymap.keys_for(action).join("/");
+    format!(
+        "{} pause, {} theme, {} mute, {} keys, {} quit",
+        keys(Action::Pause),
+        keys(Action::CycleTheme),
+        keys(Action::Mute),
+        keys(Action::Settings),
+        keys(Action::Quit)
+    )
+}
+
+fn render_lines(out: &mut impl Write, lines: &[String]) -> io::Result<()> {
+    queue!(
+        out,
+        cursor::MoveTo(0, 0),
+        terminal::Clear(terminal::ClearType::All)
+    )?;
+    for line in lines {
+        queue!(out, Print(line), Print("\r\n"))?;
+    }
+    out.flush()
+}
+
+fn render_game(
+    out: &mut impl Write,
+    game: &Game,
+    game_over: bool,
+    status: &str,
+    theme: &Theme,
+) -> io::Result<()> {
+    let cell_color = |color: [f32; 4]| {
+        if game_over {
+            to_terminal_color(blend(color, theme.game_over_overlay))
+        } else {
+            to_terminal_color(color)
+        }
+    };
+    let num_cols = game.get_num_cols();
+    let obstacles = game.get_obstacle_cells();
+    // Odd rows of a hex grid sit half a cell, one character, to the right.
+    let hex = game.get_settings().grid == Grid::Hex;
+    let mut border = "  ".repeat(num_cols + 2);
+    if hex {
+        border.push(' ');
+    }
+
+    queue!(out, cursor::MoveTo(0, 0))?;
+    queue!(
+        out,
+        SetBackgroundColor(to_terminal_color(theme.walls)),
+        Print(&border),
+        ResetColor,
+        Print("\r\n")
+    )?;
+    for row in 0..game.get_num_rows() {
+        let (left, right) = match (hex, row % 2) {
+            (false, _) => ("  ", "  "),
+            (true, 0) => ("  ", "   "),
+            (true, _) => ("   ", "  "),
+        };
+        queue!(
+            out,
+            SetBackgroundColor(to_terminal_color(theme.walls)),
+            Print(left)
+        )?;
+        for column in 0..num_cols {
+            let position = Position::new(row as i32, column as i32);
+            let obstacle = obstacles.contains(&position);
+            let portal = game
+                .get_portals()
+                .iter()
+                .position(|portal| portal.exit(position).is_some());
+            let snake = game
+                .get_snakes()
+                .iter()
+                .position(|snake| snake.get_body().contains(&position));
+            let color = if let Some(index) = snake {
+                if game.get_snakes()[index].get_body().front() == Some(&position) {
+                    theme.head_color(index)
+                } else {
+                    theme.snake_color(index)
+                }
+            } else if obstacle {
+                theme.walls
+            } else if let Some(index) = portal {
+                theme.portal_color(index)
+            } else if let Some(item) = game.food_at(position) {
+                blend(theme.background, theme.item_color(&game.get_food()[item]))
+            } else {
+                theme.background
+            };
+            queue!(out, SetBackgroundColor(cell_color(color)), Print("  "))?;
+        }
+        queue!(
+            out,
+            SetBackgroundColor(to_terminal_color(theme.walls)),
+            Print(right),
+            ResetColor,
+            Print("\r\n")
+        )?;
+    }
+    queue!(
+        out,
+        SetBackgroundColor(to_terminal_color(theme.walls)),
+        Print(&border),
+        ResetColor,
+        Print("\r\n"),
+        Print(hud_text(game)),
+        terminal::Clear(terminal::ClearType::UntilNewLine),
+        Print("\r\n"),
+        Print(status),
+        terminal::Clear(terminal::ClearType::UntilNewLine)
+    )?;
+    out.flush()
+}
+
+pub fn run(
+    options: &Options,
+    level: &Level,
+    daily: Option<Date>,
+    mut themes: Themes,
+    mut keymap: Keymap,
+) -> io::Result<()> {
+    let players = options.players;
+    let mut audio = Audio::new(options.audio);
+    // Loaded before the terminal is taken over, so a failure can be reported.
+    let save_path = daily.map_or_else(default_save_path, daily_save_path);
+    let saved_game = load_saved_game(&save_path);
+
+    let mut out = io::stdout();
+    let _guard = TerminalGuard::new(&mut out)?;
+    execute!(out, terminal::Clear(terminal::ClearType::All))?;
+
+    let mut awaiting_continue = saved_game.is_some();
+    let mut game = saved_game.unwrap_or_else(|| create_game(players, options.game, level));
+    let mut start_time = Instant::now();
+    let mut game_over_time = Instant::now();
+    let mut game_over = false;
+    let mut paused = false;
+    let mut settings: Option<SettingsScreen> = None;
+    let mut status = if awaiting_continue {
+        String::from(CONTINUE_PROMPT)
+    } else {
+        controls_help(&keymap)
+    };
+
+    loop {
+        // Wake up for the next tick, or for the restart just past the
+        // game-over pause.
+        let timeout = if game_over {
+            Duration::from_millis(GAME_OVER_SLEEP_MS as u64 + 1)
+                .saturating_sub(game_over_time.elapsed())
+        } else {
+            Duration::from_millis(tick_millis(&game) as u64).saturating_sub(start_time.elapsed())
+        };
+        if event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Release {
+                    if is_interrupt(&key) {
+                        if !awaiting_continue {
+                            autosave(&game, game_over, &save_path);
+                        }
+                        break;
+                    }
+                    let name = key_name(key.code).unwrap_or_default();
+                    if let Some(screen) = &mut settings {
+                        if !screen.handle_key(&name, &mut keymap) {
+                            settings = None;
+                            crate::save_keymap(&keymap);
+                            status = controls_help(&keymap);
+                            execute!(out, terminal::Clear(terminal::ClearType::All))?;
+                        }
+                    } else if awaiting_continue && (name == "C" || name == "N") {
+                        awaiting_continue = false;
+                        if name == "N" {
+                            discard_save(&save_path);
+                            game = create_game(players, options.game, level);
+                        }
+                        status = controls_help(&keymap);
+                    } else {
+                        match keymap.action(&name) {
+                            Some(Action::Turn { player, direction })
+                                if !awaiting_continue && !paused && !game_over =>
+                            {
+                                steer(&mut game, player, direction, options.controls);
+                            }
+                            Some(Action::Pause) if !awaiting_continue => {
+                                paused = !paused;
+                                status = if paused {
+                                    String::from(PAUSED_STATUS)
+                                } else {
+                                    controls_help(&keymap)
+                                };
+                            }
+                            Some(Action::Restart) => {
+                                awaiting_continue = false;
+                                paused = false;
+                                game_over = false;
+                                discard_save(&save_path);
+                                game = create_game(players, options.game, level);
+                                status = controls_help(&keymap);
+                            }
+                            Some(Action::Quit) => {
+                                if !awaiting_continue {
+                                    autosave(&game, game_over, &save_path);
+                                }
+                                break;
+                            }
+                            Some(Action::CycleTheme) => themes.cycle(),
+                            Some(Action::Mute) => audio.toggle_mute(),
+                            Some(Action::Settings) => settings = Some(SettingsScreen::new()),
+                            _ => (),
+                        }
+                    }
+                }
+            }
+        }
+
+        if awaiting_continue || paused || settings.is_some() {
+            start_time = Instant::now();
+        } else if game_over {
+            if game_over_time.elapsed().as_millis() > GAME_OVER_SLEEP_MS {
+                game = create_game(players, options.game, level);
+                game_over = false;
+                status = controls_help(&keymap);
+                start_time = Instant::now();
+            }
+        } else if start_time.elapsed().as_millis() > tick_millis(&game) {
+            let result = game.update_game();
+            audio.play_events(game.get_events());
+            if let Err(err) = result {
+                status = format!("Game over cause: {err}");
+                if let Some(date) = daily {
+                    status = format!("{status}. {}", record_daily(date, &game));
+                }
+                game_over = true;
+                game_over_time = Instant::now();
+                discard_save(&save_path);
+            }
+            start_time = Instant::now();
+        }
+
+        match &settings {
+            Some(screen) => render_lines(&mut out, &screen.lines(&keymap))?,
+            None => render_game(&mut out, &game, game_over, &status, themes.current())?,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn key_name_matches_piston_names() {
+        assert_eq!(key_name(KeyCode::Up).as_deref(), Some("Up"));
+        assert_eq!(key_name(KeyCode::Char('a')).as_deref(), Some("A"));
+        assert_eq!(key_name(KeyCode::Char('S')).as_deref(), Some("S"));
+        assert_eq!(key_name(KeyCode::Char('1')).as_deref(), Some("D1"));
+        assert_eq!(key_name(KeyCode::Char(' ')).as_deref(), Some("Space"));
+        assert_eq!(key_name(KeyCode::Esc).as_deref(), Some("Escape"));
+        assert_eq!(key_name(KeyCode::Enter).as_deref(), Some("Return"));
+        assert_eq!(key_name(KeyCode::F(1)).as_deref(), Some("F1"));
+        assert_eq!(key_name(KeyCode::Insert), None);
+    }
+
+    #[test]
+    fn blend_uses_overlay_alpha() {
+        let blended = blend([0.0, 0.0, 0.0, 1.0], [1.0, 0.5, 0.0, 0.5]);
+        assert_eq!(blended, [0.5, 0.25, 0.0, 1.0]);
+    }
+}
@@ -0,0 +1,85 @@
+use std::net::TcpListener;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rust_snake::game::Direction;
+use rust_snake::net::Client;
+
+/// Kills the server when the test ends, even if it fails.
+struct ServerProcess(Child);
+
+impl Drop for ServerProcess {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// Connects once the server process is listening.
+fn connect(address: &str) -> Client {
+    let start = Instant::now();
+    loop {
+        match Client::connect(address, None) {
+            Ok(client) => return client,
+            Err(err) => {
+                assert!(start.elapsed() < Duration::from_secs(10), "{err}");
+                thread::sleep(Duration::from_millis(20));
+            }
+        }
+    }
+}
+
+#[test]
+fn clients_play_against_a_server_process() {
+    let address = format!("127.0.0.1:{}", free_port());
+    let _server = ServerProcess(
+        Command::new(env!("CARGO_BIN_EXE_rust_snake"))
+            .args(["--server", &address, "--players", "2"])
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap(),
+    );
+    let mut first = connect(&address);
+    let mut second = connect(&address);
+
+    let start = Instant::now();
+    let mut turned = None;
+    loop {
+        assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+        first.poll().unwrap();
+        second.poll().unwrap();
+        let ticks = [&first, &second].map(|client| client.get_snapshot().map(|s| s.tick));
+        if let [Some(first_tick), Some(second_tick)] = ticks {
+            if turned.is_none() && first_tick >= 1 {
+                let player = first.get_player().unwrap();
+                let turn = if player == 0 {
+                    Direction::Down
+                } else {
+                    Direction::Up
+                };
+                first.send_direction(turn).unwrap();
+                turned = Some(turn);
+            }
+            if first_tick >= 5 && first_tick == second_tick {
+                break;
+            }
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+
+    // Both mirror the same game, in which the first client's turn was made.
+    assert_ne!(first.get_player(), second.get_player());
+    let snapshot = first.get_snapshot().unwrap();
+    assert_eq!(Some(snapshot), second.get_snapshot());
+    let player = first.get_player().unwrap();
+    assert_eq!(Some(snapshot.snakes[player].direction), turned);
+}